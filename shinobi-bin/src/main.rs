@@ -1,8 +1,22 @@
-use shinobi_core::{Engine, Position, START_POS};
+use shinobi_core::Engine;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     let mut engine = Engine::new();
+
+    // "shinobi-bin bench [depth]" prints the bench signature and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "bench") {
+        engine.handle_bench(args[1..].iter().map(|arg| arg.as_str()).collect());
+        return Ok(());
+    }
+
+    // "shinobi-bin tune <dataset> [epochs] [output]" tunes the evaluation and exits
+    if args.first().is_some_and(|arg| arg == "tune") {
+        engine.handle_tune(args[1..].iter().map(|arg| arg.as_str()).collect());
+        return Ok(());
+    }
+
     engine.run();
     Ok(())
 }
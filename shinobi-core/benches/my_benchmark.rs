@@ -1,16 +1,16 @@
+// Benchmarks left out of the group below are kept for one off runs
+#![allow(dead_code)]
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use modular_bitfield::prelude::*;
-use shinobi_core::mov::Move;
-use shinobi_core::mov::MoveList;
-use shinobi_core::MoveGenerator;
-use shinobi_core::{perft::*, Engine, Position, Side, START_POS};
+use shinobi_core::mov::{MoveList, MoveType};
+use shinobi_core::{perft::*, Engine, Position, Side};
 
 fn perft_starting_pos_depth_1(c: &mut Criterion) {
     let depth = 1;
     let mut engine = Engine::new();
 
     c.bench_function("Perft 1 Starting POS", |b| {
-        b.iter(|| perft(&mut engine.position, &mut engine.move_gen, black_box(depth)))
+        b.iter(|| perft(&mut engine.position, &engine.move_gen, black_box(depth)))
     });
 }
 
@@ -19,7 +19,7 @@ fn perft_starting_pos_depth_2(c: &mut Criterion) {
     let mut engine = Engine::new();
 
     c.bench_function("Perft 2 Starting POS", |b| {
-        b.iter(|| perft(&mut engine.position, &mut engine.move_gen, black_box(depth)))
+        b.iter(|| perft(&mut engine.position, &engine.move_gen, black_box(depth)))
     });
 }
 
@@ -29,7 +29,7 @@ fn perft_starting_pos_depth_3(c: &mut Criterion) {
 
     for _ in 0..5 {
         c.bench_function("Perft 3 Starting POS", |b| {
-            b.iter(|| perft(&mut engine.position, &mut engine.move_gen, black_box(depth)))
+            b.iter(|| perft(&mut engine.position, &engine.move_gen, black_box(depth)))
         });
     }
 }
@@ -39,7 +39,7 @@ fn perft_starting_pos_depth_4(c: &mut Criterion) {
     let mut engine = Engine::new();
     for _ in 0..5 {
         c.bench_function("Perft 4 Starting POS", |b| {
-            b.iter(|| perft(&mut engine.position, &mut engine.move_gen, black_box(depth)))
+            b.iter(|| perft(&mut engine.position, &engine.move_gen, black_box(depth)))
         });
     }
 }
@@ -50,7 +50,7 @@ fn perft_starting_pos_depth_5(c: &mut Criterion) {
 
     for _ in 0..5 {
         c.bench_function("Perft 5 Starting POS", |b| {
-            b.iter(|| perft(&mut engine.position, &mut engine.move_gen, black_box(depth)))
+            b.iter(|| perft(&mut engine.position, &engine.move_gen, black_box(depth)))
         });
     }
 }
@@ -74,11 +74,13 @@ fn knight_gen_bench(c: &mut Criterion) {
     for _ in 0..5 {
         c.bench_function("Generating Knight Moves", |b| {
             b.iter(|| {
-                black_box(engine.move_gen.gen_knight_moves(
+                engine.move_gen.gen_knight_moves(
                     &mut engine.position,
                     Side::White,
+                    MoveType::All,
                     &mut moves,
-                ));
+                );
+                black_box(&moves);
                 moves.clear();
             });
         });
@@ -86,17 +88,16 @@ fn knight_gen_bench(c: &mut Criterion) {
 }
 
 fn pawn_gen_bench(c: &mut Criterion) {
-    let mut engine = Engine::new();
+    let engine = Engine::new();
     let mut moves = MoveList::new();
 
     for _ in 0..5 {
         c.bench_function("Generating Pawn Moves", |b| {
             b.iter(|| {
-                black_box(
-                    engine
-                        .move_gen
-                        .gen_pawn_moves(&mut engine.position, &mut moves),
-                );
+                engine
+                    .move_gen
+                    .gen_pawn_moves(&engine.position, MoveType::All, &mut moves);
+                black_box(&moves);
                 moves.clear();
             });
         });
@@ -108,7 +109,13 @@ fn move_gen_bench(c: &mut Criterion) {
 
     for _ in 0..5 {
         c.bench_function("Generating Moves", |b| {
-            b.iter(|| black_box(engine.move_gen.generate_moves(&mut engine.position, Side::White)))
+            b.iter(|| {
+                black_box(engine.move_gen.generate_moves(
+                    &mut engine.position,
+                    Side::White,
+                    MoveType::All,
+                ))
+            })
         });
     }
 }
@@ -0,0 +1,593 @@
+use crate::pawns::{evaluate_passed_pawns, passed_pawns, pawn_structure, PawnTable};
+use crate::pieces::{bishop_pair, king_safety, mobility, outposts, rooks};
+use crate::MoveGenerator;
+use crate::Piece;
+use crate::Position;
+use crate::Side;
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use strum::IntoEnumIterator;
+
+/**
+ * A middlegame and an endgame value, blended by the game phase
+ * */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+
+    /**
+     * Interpolates between the endgame value at phase 0 and the middlegame value at MAX_PHASE
+     * */
+    pub fn taper(&self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+/**
+ * A weight of the evaluation, the score of a side is the sum of every weight times how much it
+ * counts for that side
+ * */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Param {
+    PieceValue(Piece),
+
+    /// Piece square table entry, indexed like the tables with A8 first
+    PieceSquare(Piece, usize),
+
+    /// Indexed by the rank of the pawn seen from its own side
+    PassedPawn(usize),
+    ConnectedPawn(usize),
+    IsolatedPawn,
+    DoubledPawn,
+    BackwardPawn,
+    PassedTheirKingDistance,
+    PassedOurKingDistance,
+    UnstoppablePassedPawn,
+    Mobility(Piece),
+    KingAttack(Piece),
+    PawnShield,
+    KingSemiOpenFile,
+    KingOpenFile,
+    RookOpenFile,
+    RookSemiOpenFile,
+    RookSeventhRank,
+    KnightOutpost,
+    BishopPair,
+}
+
+/**
+ * Told how much each parameter counts for a side as a position is evaluated, used by the tuner
+ * */
+pub trait Tracer {
+    fn add(&mut self, param: Param, side: Side, count: f64);
+}
+
+/// Evaluating without recording anything
+impl Tracer for () {
+    fn add(&mut self, _param: Param, _side: Side, _count: f64) {}
+}
+
+// Phase of the starting position, every piece but pawns and kings still on the board
+pub const MAX_PHASE: i32 = 24;
+
+// Weight of each piece in the game phase
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+pub const PIECE_VALUES: [Score; 6] = [
+    Score::new(82, 94),
+    Score::new(337, 281),
+    Score::new(365, 297),
+    Score::new(477, 512),
+    Score::new(1025, 936),
+    Score::new(0, 0),
+];
+
+// PIECE SQUARE TABLES
+//
+// PSQT FORMAT
+/*
+ * A8  B8  C8  D8  E8  F8  G8  H8
+ * A7  B7  C7  D7  E7  F7  G7  H7
+ * A6  B6  C6  D6  E6  F6  G6  H6
+ * A5  B5  C5  D5  E5  F5  G5  H5
+ * A4  B4  C4  D4  E4  F4  G4  H4
+ * A3  B3  C3  D3  E3  F3  G3  H3
+ * A2  B2  C2  D2  E2  F2  G2  H2
+ * A1  B1  C1  D1  E1  F1  G1  H1
+ */
+
+#[rustfmt::skip]
+const FLIP: [usize; 64]  = [
+    56,  57,  58,  59,  60,  61,  62,  63,
+    48,  49,  50,  51,  52,  53,  54,  55,
+    40,  41,  42,  43,  44,  45,  46,  47,
+    32,  33,  34,  35,  36,  37,  38,  39,
+    24,  25,  26,  27,  28,  29,  30,  31,
+    16,  17,  18,  19,  20,  21,  22,  23,
+    8,   9,   10,  11,  12,  13,  14,  15,
+    0,   1,   2,   3,   4,   5,   6,   7
+];
+
+#[rustfmt::skip]
+const MG_PAWNS_SQ: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     98, 134,  61,  95,  68, 126,  34, -11,
+     -6,   7,  26,  31,  65,  56,  25, -20,
+    -14,  13,   6,  21,  23,  12,  17, -23,
+    -27,  -2,  -5,  12,  17,   6,  10, -25,
+    -26,  -4,  -4, -10,   3,   3,  33, -12,
+    -35,  -1, -20, -23, -15,  24,  38, -22,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWNS_SQ: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+     94, 100,  85,  67,  56,  53,  82,  84,
+     32,  24,  13,   5,  -2,   4,  17,  17,
+     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+      4,   7,  -6,   1,   0,  -5,  -1,  -8,
+     13,   8,   8,  10,  13,   0,   2,  -7,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const MG_KNIGHT_SQ: [i32; 64] = [
+    -167, -89, -34, -49,  61, -97, -15, -107,
+     -73, -41,  72,  36,  23,  62,   7,  -17,
+     -47,  60,  37,  65,  84, 129,  73,   44,
+      -9,  17,  19,  53,  37,  69,  18,   22,
+     -13,   4,  16,  13,  28,  19,  21,   -8,
+     -23,  -9,  12,  10,  19,  17,  25,  -16,
+     -29, -53, -12,  -3,  -1,  18, -14,  -19,
+    -105, -21, -58, -33, -17, -28, -19,  -23,
+];
+
+#[rustfmt::skip]
+const EG_KNIGHT_SQ: [i32; 64] = [
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25,  -8, -25,  -2,  -9, -25, -24, -52,
+    -24, -20,  10,   9,  -1,  -9, -19, -41,
+    -17,   3,  22,  22,  22,  11,   8, -18,
+    -18,  -6,  16,  25,  16,  17,   4, -18,
+    -23,  -3,  -1,  15,  10,  -3, -20, -22,
+    -42, -20, -10,  -5,  -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+];
+
+#[rustfmt::skip]
+const MG_BISHOP_SQ: [i32; 64] = [
+    -29,   4, -82, -37, -25, -42,   7,  -8,
+    -26,  16, -18, -13,  30,  59,  18, -47,
+    -16,  37,  43,  40,  35,  50,  37,  -2,
+     -4,   5,  19,  50,  37,  37,   7,  -2,
+     -6,  13,  13,  26,  34,  12,  10,   4,
+      0,  15,  15,  15,  14,  27,  18,  10,
+      4,  15,  16,   0,   7,  21,  33,   1,
+    -33,  -3, -14, -21, -13, -12, -39, -21,
+];
+
+#[rustfmt::skip]
+const EG_BISHOP_SQ: [i32; 64] = [
+    -14, -21, -11,  -8,  -7,  -9, -17, -24,
+     -8,  -4,   7, -12,  -3, -13,  -4, -14,
+      2,  -8,   0,  -1,  -2,   6,   0,   4,
+     -3,   9,  12,   9,  14,  10,   3,   2,
+     -6,   3,  13,  19,   7,  10,  -3,  -9,
+    -12,  -3,   8,  10,  13,   3,  -7, -15,
+    -14, -18,  -7,  -1,   4,  -9, -15, -27,
+    -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+];
+
+#[rustfmt::skip]
+const MG_ROOK_SQ: [i32; 64] = [
+     32,  42,  32,  51,  63,   9,  31,  43,
+     27,  32,  58,  62,  80,  67,  26,  44,
+     -5,  19,  26,  36,  17,  45,  61,  16,
+    -24, -11,   7,  26,  24,  35,  -8, -20,
+    -36, -26, -12,  -1,   9,  -7,   6, -23,
+    -45, -25, -16, -17,   3,   0,  -5, -33,
+    -44, -16, -20,  -9,  -1,  11,  -6, -71,
+    -19, -13,   1,  17,  16,   7, -37, -26,
+];
+
+#[rustfmt::skip]
+const EG_ROOK_SQ: [i32; 64] = [
+     13,  10,  18,  15,  12,  12,   8,   5,
+     11,  13,  13,  11,  -3,   3,   8,   3,
+      7,   7,   7,   5,   4,  -3,  -5,  -3,
+      4,   3,  13,   1,   2,   1,  -1,   2,
+      3,   5,   8,   4,  -5,  -6,  -8, -11,
+     -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+     -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+     -9,   2,   3,  -1,  -5, -13,   4, -20,
+];
+
+#[rustfmt::skip]
+const MG_QUEEN_SQ: [i32; 64] = [
+    -28,   0,  29,  12,  59,  44,  43,  45,
+    -24, -39,  -5,   1, -16,  57,  28,  54,
+    -13, -17,   7,   8,  29,  56,  47,  57,
+    -27, -27, -16, -16,  -1,  17,  -2,   1,
+     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+    -14,   2, -11,  -2,  -5,   2,  14,   5,
+    -35,  -8,  11,   2,   8,  15,  -3,   1,
+     -1, -18,  -9,  10, -15, -25, -31, -50,
+];
+
+#[rustfmt::skip]
+const EG_QUEEN_SQ: [i32; 64] = [
+     -9,  22,  22,  27,  27,  19,  10,  20,
+    -17,  20,  32,  41,  58,  25,  30,   0,
+    -20,   6,   9,  49,  47,  35,  19,   9,
+      3,  22,  24,  45,  57,  40,  57,  36,
+    -18,  28,  19,  47,  31,  34,  39,  23,
+    -16, -27,  15,   6,   9,  17,  10,   5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43,  -5, -32, -20, -41,
+];
+
+// Keeps the king behind its pawns while there are pieces to attack it
+#[rustfmt::skip]
+const MG_KING_SQ: [i32; 64] = [
+    -65,  23,  16, -15, -56, -34,   2,  13,
+     29,  -1, -20,  -7,  -8,  -4, -38, -29,
+     -9,  24,   2, -16, -20,   6,  22, -22,
+    -17, -20, -12, -27, -30, -25, -14, -36,
+    -49,  -1, -27, -39, -46, -44, -33, -51,
+    -14, -14, -22, -46, -44, -30, -15, -27,
+      1,   7,  -8, -64, -43, -16,   9,   8,
+    -15,  36,  12, -54,   8, -28,  24,  14,
+];
+
+// Brings the king to the centre once the pieces are traded off
+#[rustfmt::skip]
+const EG_KING_SQ: [i32; 64] = [
+    -74, -35, -18, -18, -11,  15,   4, -17,
+    -12,  17,  14,  17,  17,  38,  23,  11,
+     10,  17,  23,  15,  20,  45,  44,  13,
+     -8,  22,  24,  27,  26,  33,  26,   3,
+    -18,  -4,  21,  24,  27,  23,   9, -11,
+    -19,  -3,  11,  21,  23,  16,   7,  -9,
+    -27, -11,   4,  13,  14,   4,  -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+pub const MG_TABLES: [[i32; 64]; 6] = [
+    MG_PAWNS_SQ,
+    MG_KNIGHT_SQ,
+    MG_BISHOP_SQ,
+    MG_ROOK_SQ,
+    MG_QUEEN_SQ,
+    MG_KING_SQ,
+];
+
+pub const EG_TABLES: [[i32; 64]; 6] = [
+    EG_PAWNS_SQ,
+    EG_KNIGHT_SQ,
+    EG_BISHOP_SQ,
+    EG_ROOK_SQ,
+    EG_QUEEN_SQ,
+    EG_KING_SQ,
+];
+
+/**
+ * Material plus piece square value of a piece of side on square
+ * */
+pub fn piece_square(piece: Piece, side: Side, square: usize) -> Score {
+    let index = table_index(side, square);
+
+    PIECE_VALUES[piece as usize]
+        + Score::new(
+            MG_TABLES[piece as usize][index],
+            EG_TABLES[piece as usize][index],
+        )
+}
+
+/**
+ * Index into the piece square tables of square for side
+ * */
+pub fn table_index(side: Side, square: usize) -> usize {
+    // Tables are written from White's side with A8 first
+    match side {
+        Side::White => FLIP[square],
+        Side::Black => square,
+    }
+}
+
+/**
+ * Phase contributed by the pieces of side, 12 for a full set
+ * */
+pub fn side_phase(position: &Position, side: Side) -> i32 {
+    Piece::iter()
+        .map(|piece| {
+            let count = position.piece_bitboard(piece, side).0.count_ones() as i32;
+            count * PHASE_WEIGHTS[piece as usize]
+        })
+        .sum()
+}
+
+/**
+ * MAX_PHASE with all pieces on the board down to 0 with only pawns and kings left
+ *
+ * Promotions can push the sum past MAX_PHASE, so it is capped there
+ * */
+pub fn game_phase(position: &Position) -> i32 {
+    let phase = side_phase(position, Side::White) + side_phase(position, Side::Black);
+    phase.min(MAX_PHASE)
+}
+
+/**
+ * Material and piece square score of White minus Black, counted from scratch
+ *
+ * Position keeps the same score in `psqt` as moves are made
+ * */
+pub fn material_psqt(position: &Position) -> Score {
+    let mut score = Score::default();
+
+    for side in Side::iter() {
+        for piece in Piece::iter() {
+            for square in position.piece_bitboard(piece, side) {
+                match side {
+                    Side::White => score += piece_square(piece, side, square as usize),
+                    Side::Black => score -= piece_square(piece, side, square as usize),
+                }
+            }
+        }
+    }
+
+    score
+}
+
+/**
+ * Mobility, king safety, rooks, outposts and the bishop pair of side
+ * */
+pub fn piece_activity(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    side: Side,
+    tracer: &mut impl Tracer,
+) -> Score {
+    mobility(position, move_gen, side, tracer)
+        + king_safety(position, move_gen, side, tracer)
+        + rooks(position, side, tracer)
+        + outposts(position, move_gen, side, tracer)
+        + bishop_pair(position, side, tracer)
+}
+
+/**
+ * Static evaluation of position from the side to move's point of view
+ *
+ * Pawn structure comes from pawn_table when the same pawns were evaluated before
+ * */
+pub fn evaluate(position: &Position, move_gen: &MoveGenerator, pawn_table: &mut PawnTable) -> i32 {
+    let pawns = pawn_table.probe(position);
+    let score = position.psqt
+        + pawns.score
+        + evaluate_passed_pawns(position, &pawns)
+        + piece_activity(position, move_gen, Side::White, &mut ())
+        - piece_activity(position, move_gen, Side::Black, &mut ());
+    let score = score.taper(position.phase.min(MAX_PHASE));
+
+    match position.state.current_turn() {
+        Side::White => score,
+        Side::Black => -score,
+    }
+}
+
+/**
+ * Evaluates position from scratch, White minus Black before tapering, telling tracer how much
+ * every parameter counts for each side
+ * */
+pub fn evaluate_params(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    tracer: &mut impl Tracer,
+) -> Score {
+    let mut score = Score::default();
+
+    for side in Side::iter() {
+        let mut side_score = Score::default();
+
+        for piece in Piece::iter() {
+            for square in position.piece_bitboard(piece, side) {
+                let index = table_index(side, square as usize);
+                side_score += piece_square(piece, side, square as usize);
+                tracer.add(Param::PieceValue(piece), side, 1.0);
+                tracer.add(Param::PieceSquare(piece, index), side, 1.0);
+            }
+        }
+
+        let (pawns, passed) = pawn_structure(position, side, tracer);
+        side_score += pawns
+            + passed_pawns(position, side, passed, tracer)
+            + piece_activity(position, move_gen, side, tracer);
+
+        match side {
+            Side::White => score += side_score,
+            Side::Black => score -= side_score,
+        }
+    }
+
+    score
+}
+
+/**
+ * One evaluation term split by side
+ * */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceTerm {
+    pub name: &'static str,
+    pub white: Score,
+    pub black: Score,
+}
+
+/**
+ * Every term of the evaluation of a position, for finding out why it was misjudged
+ * */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalTrace {
+    pub terms: Vec<TraceTerm>,
+    pub phase: i32,
+
+    /// Tapered score from the side to move's point of view, the same as evaluate
+    pub score: i32,
+}
+
+impl EvalTrace {
+    /**
+     * Sum of every term, White minus Black, before tapering
+     * */
+    pub fn total(&self) -> Score {
+        self.terms.iter().fold(Score::default(), |total, term| {
+            total + term.white - term.black
+        })
+    }
+}
+
+/**
+ * Evaluates position term by term, without the pawn table
+ * */
+pub fn trace(position: &Position, move_gen: &MoveGenerator) -> EvalTrace {
+    let term = |name: &'static str, by_side: &dyn Fn(Side) -> Score| TraceTerm {
+        name,
+        white: by_side(Side::White),
+        black: by_side(Side::Black),
+    };
+
+    let material = |side: Side| {
+        Piece::iter().fold(Score::default(), |total, piece| {
+            let count = position.piece_bitboard(piece, side).0.count_ones() as i32;
+            total + PIECE_VALUES[piece as usize] * count
+        })
+    };
+
+    let piece_squares = |side: Side| {
+        let mut score = Score::default();
+        for piece in Piece::iter() {
+            for square in position.piece_bitboard(piece, side) {
+                score += piece_square(piece, side, square as usize) - PIECE_VALUES[piece as usize];
+            }
+        }
+        score
+    };
+
+    let terms = vec![
+        term("Material", &material),
+        term("Piece squares", &piece_squares),
+        term("Pawns", &|side| pawn_structure(position, side, &mut ()).0),
+        term("Passed pawns", &|side| {
+            let passed = pawn_structure(position, side, &mut ()).1;
+            passed_pawns(position, side, passed, &mut ())
+        }),
+        term("Mobility", &|side| {
+            mobility(position, move_gen, side, &mut ())
+        }),
+        term("King safety", &|side| {
+            king_safety(position, move_gen, side, &mut ())
+        }),
+        term("Rooks", &|side| rooks(position, side, &mut ())),
+        term("Outposts", &|side| {
+            outposts(position, move_gen, side, &mut ())
+        }),
+        term("Bishop pair", &|side| bishop_pair(position, side, &mut ())),
+    ];
+
+    let mut trace = EvalTrace {
+        terms,
+        phase: position.phase.min(MAX_PHASE),
+        score: 0,
+    };
+
+    let score = trace.total().taper(trace.phase);
+    trace.score = match position.state.current_turn() {
+        Side::White => score,
+        Side::Black => -score,
+    };
+
+    trace
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter, name: &str, white: Score, black: Score| {
+            let total = white - black;
+            writeln!(
+                f,
+                "{:>13} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}",
+                name, white.mg, white.eg, black.mg, black.eg, total.mg, total.eg
+            )
+        };
+
+        writeln!(f, "         Term |    White    |    Black    |    Total")?;
+        writeln!(f, "              |   MG    EG  |   MG    EG  |   MG    EG")?;
+        writeln!(f, "--------------+-------------+-------------+------------")?;
+
+        let mut white = Score::default();
+        let mut black = Score::default();
+        for term in self.terms.iter() {
+            row(f, term.name, term.white, term.black)?;
+            white += term.white;
+            black += term.black;
+        }
+
+        writeln!(f, "--------------+-------------+-------------+------------")?;
+        row(f, "Total", white, black)?;
+        writeln!(f)?;
+        writeln!(f, "Phase: {} / {}", self.phase, MAX_PHASE)?;
+        write!(f, "Final evaluation: {} (side to move)", self.score)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::eval;
+use crate::mov::Move;
+use crate::pawns::PawnTable;
+use crate::MoveGenerator;
+use crate::Position;
+
+/**
+ * Static evaluation used by the search, set with `Search::set_evaluator`
+ *
+ * Each clone of the search owns its own evaluator. The hooks let an evaluator keep state
+ * in step with the position as the search walks the tree, they do nothing by default
+ * */
+pub trait Evaluator: Send {
+    /// Score of position from the side to move, in centipawns
+    fn evaluate(&mut self, position: &Position, move_gen: &MoveGenerator) -> i32;
+
+    /// Called with the root position before every search
+    fn on_new_search(&mut self, _position: &Position) {}
+
+    /// Called with the position before mv is made on it
+    fn on_make_move(&mut self, _position: &Position, _mv: Move) {}
+
+    /// Called with the position after the last move was taken back
+    fn on_unmake_move(&mut self, _position: &Position) {}
+
+    /// Called with the position before a null move is made on it
+    fn on_make_null_move(&mut self, _position: &Position) {}
+
+    /// Called with the position after the null move was taken back
+    fn on_unmake_null_move(&mut self, _position: &Position) {}
+
+    /// Forgets everything cached from earlier searches
+    fn clear(&mut self) {}
+
+    fn box_clone(&self) -> Box<dyn Evaluator>;
+}
+
+impl Clone for Box<dyn Evaluator> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/**
+ * Hand written evaluation from the eval module, with its own pawn hash table
+ * */
+#[derive(Debug, Clone, Default)]
+pub struct ClassicalEvaluator {
+    pawn_table: PawnTable,
+}
+
+impl ClassicalEvaluator {
+    pub fn new() -> ClassicalEvaluator {
+        ClassicalEvaluator {
+            pawn_table: PawnTable::new(),
+        }
+    }
+}
+
+impl Evaluator for ClassicalEvaluator {
+    fn evaluate(&mut self, position: &Position, move_gen: &MoveGenerator) -> i32 {
+        eval::evaluate(position, move_gen, &mut self.pawn_table)
+    }
+
+    fn clear(&mut self) {
+        self.pawn_table.clear();
+    }
+
+    fn box_clone(&self) -> Box<dyn Evaluator> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::limits::SearchLimits;
+use crate::pv::SearchResult;
+use crate::search::Search;
+use crate::MoveGenerator;
+use crate::Position;
+use crate::SearchInfo;
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+// Deep recursion in unoptimized builds needs more than the default 2 MiB
+const SEARCH_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/**
+ * A search running on its own thread
+ *
+ * Every handle gets its own stop flag, raised before the thread starts, so a stop can not be
+ * lost or reach another search. Dropping the handle stops the search and waits for it.
+ * */
+pub struct SearchHandle {
+    searching: Arc<AtomicBool>,
+    infinite: bool,
+    thread: Option<JoinHandle<SearchResult>>,
+}
+
+impl SearchHandle {
+    pub fn spawn(
+        mut search: Search,
+        position: Position,
+        move_gen: Arc<MoveGenerator>,
+        limits: SearchLimits,
+    ) -> io::Result<SearchHandle> {
+        let searching = Arc::new(AtomicBool::new(true));
+        let infinite = limits.infinite;
+        let mut info = SearchInfo::from_limits(limits, position.state.current_turn());
+
+        search.searching = searching.clone();
+        let thread = thread::Builder::new()
+            .name("search".into())
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(move || {
+                let mut position = position;
+                let result = search.search_position(&mut info, &mut position, &move_gen);
+                search.searching.store(false, Ordering::Relaxed);
+                result
+            })?;
+
+        Ok(SearchHandle {
+            searching,
+            infinite,
+            thread: Some(thread),
+        })
+    }
+
+    /**
+     * Asks the search to stop, it still reports its best move
+     * */
+    pub fn stop(&self) {
+        self.searching.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /**
+     * True for "go infinite", which only ends when stopped
+     * */
+    pub fn is_infinite(&self) -> bool {
+        self.infinite
+    }
+
+    /**
+     * Waits for the search to finish without stopping it
+     * */
+    pub fn wait(&mut self) -> Option<SearchResult> {
+        let thread = self.thread.take()?;
+        match thread.join() {
+            Ok(result) => Some(result),
+            Err(_) => {
+                log::error!("Search thread panicked");
+                None
+            }
+        }
+    }
+
+    /**
+     * Stops the search and waits for it to report its best move
+     * */
+    pub fn stop_and_wait(&mut self) -> Option<SearchResult> {
+        self.stop();
+        self.wait()
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.stop_and_wait();
+    }
+}
@@ -0,0 +1,47 @@
+use crate::mov::Move;
+
+/**
+ * Limits of a search started with `Search::go`, limits that are not set do not stop the search
+ *
+ * Times are in milliseconds
+ * */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<i32>,
+    pub move_time: Option<i32>,
+    pub w_time: Option<i32>,
+    pub b_time: Option<i32>,
+    pub w_inc: Option<i32>,
+    pub b_inc: Option<i32>,
+    pub moves_to_go: Option<i32>,
+
+    /// Stops after this many nodes, the same count always gives the same search
+    pub nodes: Option<u64>,
+    pub infinite: bool,
+
+    /// Only these moves are searched at the root, every move if empty
+    pub search_moves: Vec<Move>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: i32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn move_time(move_time: i32) -> SearchLimits {
+        SearchLimits {
+            move_time: Some(move_time),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+}
@@ -1,26 +1,65 @@
-pub mod bot;
+pub mod bench;
+pub mod eval;
+pub mod evaluator;
+pub mod handle;
+pub mod limits;
+pub mod observer;
+pub mod params;
+pub mod pawns;
+pub mod pieces;
 pub mod pv;
 pub mod search;
+pub mod skill;
 pub mod tt;
+pub mod tune;
 pub mod zobrist;
 
-use crate::mov::MoveType;
 use crate::get_time_ms;
 use crate::mov::Move;
+use crate::mov::MoveType;
 use crate::MoveGenerator;
 use crate::Position;
+use crate::Side;
 use crate::Zobrist;
 use crate::START_POS;
-use search::{Search, MAX_DEPTH};
+use bench::BENCH_DEPTH;
+use handle::SearchHandle;
+use limits::SearchLimits;
+use observer::UciObserver;
+use params::SearchParams;
+use search::{Search, MAX_MULTI_PV};
 use serde::{ser::SerializeStruct, Serialize};
+use tune::Tuner;
 
 use std::iter::Peekable;
 use std::slice;
 use std::str::FromStr;
-use std::sync::atomic::Ordering;
-use std::thread::JoinHandle;
+use std::sync::{Arc, Mutex};
 
 const MAX_TIME_MS: i32 = 200;
+
+// Tuning runs this many epochs unless told otherwise, reporting and saving every so often
+const TUNE_EPOCHS: usize = 1000;
+const TUNE_REPORT_EPOCHS: usize = 50;
+const TUNE_OUTPUT: &str = "tuned.rs";
+
+// Time management
+const DEFAULT_MOVES_TO_GO: i32 = 30;
+const MOVE_OVERHEAD_MS: i32 = 50;
+const GO_KEYWORDS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
 type PeekStrIter<'a> = Peekable<slice::Iter<'a, &'a str>>;
 
 #[derive(Serialize)]
@@ -29,60 +68,91 @@ pub enum EngineMode {
     Thinking,
 }
 
+/**
+ * Limits of the running search and the time it has to stop by
+ * */
 #[derive(Debug, Clone)]
 pub struct SearchInfo {
-    search_moves: Vec<Move>,
-    ponder: bool,
-    w_time: Option<i32>,
-    b_time: Option<i32>,
-    w_inc: Option<i32>,
-    b_inc: Option<i32>,
-    moves_to_go: Option<i32>,
-    depth: Option<i32>,
-    nodes: Option<u32>,
-    mate: Option<u32>,
-    move_time: Option<i32>,
-    infinite: bool,
+    limits: SearchLimits,
     start_time: Option<i32>,
     stop_time: Option<i32>,
     stopped: bool,
 }
 
 impl SearchInfo {
-    fn new() -> SearchInfo {
+    pub fn new() -> SearchInfo {
         SearchInfo {
-            search_moves: Vec::new(),
-            ponder: false,
-            w_time: None,
-            b_time: None,
-            w_inc: None,
-            b_inc: None,
-            moves_to_go: None,
-            depth: None,
-            nodes: None,
-            mate: None,
-            move_time: None,
-            infinite: false,
+            limits: SearchLimits::default(),
             start_time: None,
             stop_time: None,
             stopped: false,
         }
     }
 
-    fn reset(&mut self) {
-        *self = SearchInfo::new();
+    /**
+     * Starts the clock for a search within `limits` by `side`
+     * */
+    pub fn from_limits(limits: SearchLimits, side: Side) -> SearchInfo {
+        let start_time = get_time_ms();
+        let stop_time = SearchInfo::time_budget(&limits, side).map(|time| start_time + time);
+
+        SearchInfo {
+            limits,
+            start_time: Some(start_time),
+            stop_time,
+            stopped: false,
+        }
+    }
+
+    /**
+     * Milliseconds side may spend on this move, None if the search is not timed
+     * */
+    fn time_budget(limits: &SearchLimits, side: Side) -> Option<i32> {
+        if limits.infinite {
+            return None;
+        }
+
+        if let Some(move_time) = limits.move_time {
+            return Some((move_time - MOVE_OVERHEAD_MS).max(1));
+        }
+
+        let (time, inc) = match side {
+            Side::White => (limits.w_time, limits.w_inc),
+            Side::Black => (limits.b_time, limits.b_inc),
+        };
+
+        time.map(|time| {
+            let moves_to_go = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let budget = time / moves_to_go + inc.unwrap_or(0);
+
+            // Never plan to use more than is left on the clock
+            budget.min(time - MOVE_OVERHEAD_MS).max(1)
+        })
+    }
+
+    pub fn limits(&self) -> &SearchLimits {
+        &self.limits
+    }
+
+    pub fn search_moves(&self) -> &[Move] {
+        &self.limits.search_moves
+    }
+}
+
+impl Default for SearchInfo {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub struct Engine {
     pub position: Position,
-    pub move_gen: MoveGenerator,
+    pub move_gen: Arc<MoveGenerator>,
     pub zobrist: Zobrist,
     pub debug: bool,
     pub mode: EngineMode,
-    pub info: SearchInfo,
     pub search: Search,
-    search_thread: Option<JoinHandle<()>>,
+    search_handle: Option<SearchHandle>,
 }
 
 impl Serialize for Engine {
@@ -100,41 +170,62 @@ impl Serialize for Engine {
 impl Engine {
     pub fn new() -> Engine {
         let position = Position::default();
+        let mut search = Search::new();
+        search.set_observer(Arc::new(Mutex::new(UciObserver)));
+
         Engine {
             position,
-            move_gen: MoveGenerator::new(),
+            move_gen: Arc::new(MoveGenerator::new()),
             zobrist: Zobrist::new(),
             debug: false,
             mode: EngineMode::Waiting,
-            info: SearchInfo::new(),
-            search: Search::new(),
-            search_thread: None,
+            search,
+            search_handle: None,
         }
     }
 
     pub fn run(&mut self) {
         loop {
             let mut input = String::new();
-            std::io::stdin()
+            let read = std::io::stdin()
                 .read_line(&mut input)
                 .expect("Can not read user input");
 
-            let command = input.trim();
-            self.handle_command(command);
+            // End of input is treated like quit
+            if read == 0 || !self.handle_command(input.trim()) {
+                break;
+            }
         }
+
+        self.stop_search();
     }
 
-    fn handle_command(&mut self, command: &str) {
+    /**
+     * Handles one UCI command, returns false once the engine should quit
+     * */
+    fn handle_command(&mut self, command: &str) -> bool {
         let mut arguments: Vec<&str> = command.split_whitespace().collect();
+        if arguments.is_empty() {
+            return true;
+        }
         let command = arguments.remove(0);
 
         match command {
             "d" => println!("{}", self.position),
+            "eval" => println!("{}", eval::trace(&self.position, &self.move_gen)),
             "uci" => self.handle_uci(),
             "debug" => self.debug = !self.debug,
-            "isready" => println!("readyok"),
-            "ucinewgame" => self.search = Search::new(),
-            "setoption" => (),
+            "isready" => self.handle_isready(),
+            "ucinewgame" => {
+                self.stop_search();
+                self.search.clear();
+            }
+            "setoption" => match self.handle_setoption(arguments) {
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("{}", e);
+                }
+            },
             "position" => match self.handle_position(arguments) {
                 Ok(_) => {}
                 Err(e) => {
@@ -144,16 +235,103 @@ impl Engine {
             "go" => {
                 self.handle_go(arguments);
             }
-            "stop" => {
-                self.handle_stop();
-                if let Some(search_th) = self.search_thread.take() {
-                    search_th.join().expect("Fatal Thread");
+            "stop" => self.stop_search(),
+            "bench" => self.handle_bench(arguments),
+            "quit" => return false,
+            _ => log::error!("Unknown command: {}", command),
+        }
+
+        true
+    }
+
+    /**
+     * Runs the bench at the given depth, or the default one, and prints the totals
+     * */
+    pub fn handle_bench(&mut self, args: Vec<&str>) {
+        let depth = match args.first() {
+            Some(depth) => match depth.parse::<i32>() {
+                Ok(depth) => depth,
+                Err(_) => {
+                    log::error!("Invalid bench depth: {}", depth);
+                    return;
                 }
+            },
+            None => BENCH_DEPTH,
+        };
+
+        self.stop_search();
+        let result = bench::bench(&self.move_gen, depth);
+
+        println!("Total time (ms) : {}", result.time);
+        println!("Nodes searched  : {}", result.nodes);
+        println!("Nodes/second    : {}", result.nps());
+    }
+
+    /**
+     * Tunes the evaluation on a dataset of positions and results, see tune::parse_entry,
+     * and writes the tuned constants as Rust source
+     *
+     * Arguments are the dataset, the number of epochs and the output file
+     * */
+    pub fn handle_tune(&mut self, args: Vec<&str>) {
+        let dataset = match args.first() {
+            Some(dataset) => dataset,
+            None => {
+                log::error!("Usage: tune <dataset> [epochs] [output]");
+                return;
+            }
+        };
+        let epochs = match args.get(1) {
+            Some(epochs) => match epochs.parse::<usize>() {
+                Ok(epochs) => epochs,
+                Err(_) => {
+                    log::error!("Invalid number of epochs: {}", epochs);
+                    return;
+                }
+            },
+            None => TUNE_EPOCHS,
+        };
+        let output = args.get(2).copied().unwrap_or(TUNE_OUTPUT);
+
+        let mut tuner = Tuner::new();
+        match tuner.load(dataset, &self.move_gen) {
+            Ok(positions) => println!("Loaded {} positions", positions),
+            Err(err) => {
+                log::error!("{}", err);
+                return;
             }
+        }
 
-            "quit" => std::process::exit(0),
-            _ => log::error!("Unknown command: {}", command),
+        println!("K {:.3} error {:.6}", tuner.fit_k(), tuner.error());
+
+        for epoch in 1..=epochs {
+            let error = tuner.epoch(tune::LEARNING_RATE);
+
+            if epoch % TUNE_REPORT_EPOCHS == 0 || epoch == epochs {
+                println!("Epoch {} error {:.6}", epoch, error);
+                if let Err(err) = std::fs::write(output, tuner.to_rust()) {
+                    log::error!("{}: {}", output, err);
+                    return;
+                }
+            }
         }
+
+        println!("Tuned constants written to {}", output);
+    }
+
+    /**
+     * Waits for a running search to print its best move first, unless it is infinite and
+     * would only end with a stop
+     * */
+    fn handle_isready(&mut self) {
+        if let Some(handle) = self.search_handle.as_mut() {
+            if !handle.is_infinite() {
+                handle.wait();
+                self.search_handle = None;
+            }
+        }
+
+        println!("readyok");
     }
 
     fn handle_uci(&self) {
@@ -161,187 +339,117 @@ impl Engine {
         println!("id author Lajuan");
         println!();
 
+        println!(
+            "option name MultiPV type spin default 1 min 1 max {}",
+            MAX_MULTI_PV
+        );
+        for option in SearchParams::uci_options() {
+            println!("{}", option);
+        }
+
         println!("uciok");
     }
 
-    fn parse_go_commands<T: FromStr>(&mut self, iterator: &mut PeekStrIter, data: &mut Option<T>) {
-        if let Some(time) = iterator.peek() {
-            match time.parse::<T>() {
-                Ok(v) => {
-                    *data = Some(v);
-                    self.info.infinite = false
-                }
-
-                Err(_) => {
-                    self.info.infinite = false;
-                }
-            }
-        } else {
-            self.info.infinite = true;
+    fn parse_go_commands<T: FromStr>(iterator: &mut PeekStrIter, data: &mut Option<T>) {
+        match iterator.next() {
+            Some(value) => match value.parse::<T>() {
+                Ok(v) => *data = Some(v),
+                Err(_) => log::error!("Invalid go value: {}", value),
+            },
+            None => log::error!("Missing go value"),
         }
     }
 
     fn handle_go(&mut self, args: Vec<&str>) {
-        self.info.reset();
-        let mut time = None;
-        let mut inc = 0;
+        let mut limits = SearchLimits::default();
         let mut iterator: Peekable<slice::Iter<'_, &str>> = args.iter().peekable();
         while let Some(arg) = iterator.next() {
             match *arg {
-                "wtime" => {
-                    let mut data = self.info.w_time;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.w_time = data;
-                    time = data;
-                }
-                "btime" => {
-                    let mut data = self.info.b_time;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.b_time = data;
-                    time = data;
-                }
-                "winc" => {
-                    let mut data = self.info.w_inc;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.w_inc = data;
-                    inc = data.unwrap();
+                "wtime" => Self::parse_go_commands(&mut iterator, &mut limits.w_time),
+                "btime" => Self::parse_go_commands(&mut iterator, &mut limits.b_time),
+                "winc" => Self::parse_go_commands(&mut iterator, &mut limits.w_inc),
+                "binc" => Self::parse_go_commands(&mut iterator, &mut limits.b_inc),
+                "movestogo" => Self::parse_go_commands(&mut iterator, &mut limits.moves_to_go),
+                "depth" => Self::parse_go_commands(&mut iterator, &mut limits.depth),
+                "movetime" => Self::parse_go_commands(&mut iterator, &mut limits.move_time),
+                "nodes" => Self::parse_go_commands(&mut iterator, &mut limits.nodes),
+                "infinite" => limits.infinite = true,
+
+                "searchmoves" => {
+                    while let Some(mv) = iterator.next_if(|arg| !GO_KEYWORDS.contains(arg)) {
+                        match self.parse_move(mv) {
+                            Some(mv) => limits.search_moves.push(mv),
+                            None => log::error!("Invalid searchmoves move: {}", mv),
+                        }
+                    }
                 }
-                "binc" => {
-                    let mut data = self.info.b_inc;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.b_inc = data;
-                    inc = data.unwrap();
-                }
-                "movestogo" => {
-                    let mut data = self.info.moves_to_go;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.moves_to_go = data;
-                }
-                "depth" => {
-                    let mut data = self.info.depth;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.depth = data;
-                }
-
-                /*
-                "nodes" => {
-                    let mut data = self.info.nodes;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.nodes = data;
-                }
-
-                "mate" => {
-                    let mut data = self.info.mate;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.mate = data;
-                }
-
-                "movetime" => {
-                    let mut data = self.info.move_time;
-                    self.parse_go_commands(&mut iterator, &mut data);
-                    self.info.move_time = data;
-                }
-
-                "ponder" => self.info.ponder = true,
-
-                "infinite" => self.info.infinite = true,
-                */
                 _ => (),
             }
         }
 
-        /*
-        if let Some(_) = self.info.move_time {
-            time = self.info.move_time;
-            self.info.moves_to_go = Some(1);
+        log::info!("LIMITS: {:?}", limits);
+
+        // The previous search reports its best move before this one starts
+        self.stop_search();
+        match SearchHandle::spawn(
+            self.search.clone(),
+            self.position.clone(),
+            self.move_gen.clone(),
+            limits,
+        ) {
+            Ok(handle) => self.search_handle = Some(handle),
+            Err(err) => log::error!("Could not start the search: {}", err),
         }
-        */
+    }
 
-        self.info.start_time = Some(get_time_ms());
+    fn handle_setoption(&mut self, args: Vec<&str>) -> Result<(), String> {
+        self.stop_search();
 
-        if let Some(t) = time {
-            self.info.infinite = false;
-            time = Some(t / self.info.moves_to_go.unwrap());
-            time = Some(t - 50);
-            self.info.stop_time = Some(self.info.start_time.unwrap() as i32 + t as i32 + inc);
-        }
+        let mut name: Vec<&str> = Vec::new();
+        let mut value: Vec<&str> = Vec::new();
+        let mut parsing_value = false;
 
-        log::info!(
-            "TIME: {:#?} START: {:?} STOP: {:?} DEPTH: {:?} TIMESET: {:?}",
-            time,
-            self.info.start_time,
-            self.info.stop_time,
-            self.info.depth,
-            !self.info.infinite
-        );
-
-        if let Some(depth) = self.info.depth {
-            log::info!("DEPTH SEEN");
-            let arc_mg = std::sync::Arc::new(self.move_gen);
-            let mut pos = self.position.clone();
-            let mut search = self.search.clone();
-            search.best_move = None;
-            let mut info = self.info.clone();
-            self.search_thread = Some(std::thread::spawn(move || {
-                search.search_position(&mut info, &mut pos, &arc_mg, depth);
-            }));
-        } else {
-            log::info!("MAX DEPTH");
-            let arc_mg = std::sync::Arc::new(self.move_gen);
-            let mut pos = self.position.clone();
-            let mut search = self.search.clone();
-            search.best_move = None;
-            let mut info = self.info.clone();
-            self.search_thread = Some(std::thread::spawn(move || {
-                search.search_position(&mut info, &mut pos, &arc_mg, MAX_DEPTH);
-            }));
+        if args.first() != Some(&"name") {
+            return Err("MISSING OPTION NAME".to_string());
         }
 
-        self.search.searching.store(true, Ordering::Relaxed);
-
-        /*
-        if args[1] == "depth" {
-            log::info!("DEPTH SEEN");
-            let d = args[2].parse();
-            match d {
-                Ok(depth) => {
-                    let arc_mg = Arc::new(self.move_gen);
-                    let mut pos = self.position.clone();
-                    let mut search = self.search.clone();
-                    search.best_move = None;
-                    self.search_thread = Some(thread::spawn(move || {
-                        search.search_position(&mut pos, &arc_mg, depth);
-                    }));
-                }
-                Err(_) => {
-                    let arc_mg = Arc::new(self.move_gen);
-                    let mut pos = self.position.clone();
-                    let mut search = self.search.clone();
-                    search.best_move = None;
-                    self.search_thread = Some(thread::spawn(move || {
-                        search.search_position(&mut pos, &arc_mg, MAX_DEPTH);
-                    }));
-                }
+        for arg in args[1..].iter() {
+            if *arg == "value" {
+                parsing_value = true;
+            } else if parsing_value {
+                value.push(arg);
+            } else {
+                name.push(arg);
             }
-        } else {
-            log::info!("NO DEPTH SEEN");
-            let arc_mg = Arc::new(self.move_gen);
-            let mut pos = self.position.clone();
-            let mut search = self.search.clone();
-            search.best_move = None;
-            self.search_thread = Some(thread::spawn(move || {
-                search.search_position(&mut pos, &arc_mg, MAX_DEPTH);
-            }));
         }
-        */
+
+        let name = name.join(" ");
+        let value = value.join(" ");
+
+        match name.to_lowercase().as_str() {
+            "multipv" => match value.parse::<usize>() {
+                Ok(v) => self.search.multi_pv = v.clamp(1, MAX_MULTI_PV),
+                Err(_) => return Err(format!("Invalid MultiPV value: {}", value)),
+            },
+            _ => self.search.params.set_option(&name, &value)?,
+        }
+
+        Ok(())
     }
 
-    fn handle_stop(&mut self) {
-        self.search.searching.store(false, Ordering::Relaxed);
-        log::info!("STOP TRIGGERED");
+    /**
+     * Stops a running search and waits until it printed its best move
+     * */
+    fn stop_search(&mut self) {
+        if let Some(mut handle) = self.search_handle.take() {
+            log::info!("STOP TRIGGERED");
+            handle.stop_and_wait();
+        }
     }
 
     fn handle_position(&mut self, args: Vec<&str>) -> Result<(), String> {
+        self.stop_search();
+
         let mut fen: String = String::new();
         let mut parse_fen: bool = true;
         let mut moves: Vec<&str> = Vec::new();
@@ -389,18 +497,25 @@ impl Engine {
         }
 
         for mv in moves {
-            let side = self.position.state.current_turn();
-            let moves = self
-                .move_gen
-                .generate_legal_moves(&mut self.position, side, MoveType::All);
-            for j in 0..moves.len() {
-                let gen_move = moves.get(j);
-                if mv == gen_move.to_string() {
-                    self.position.make_move(gen_move);
-                }
+            if let Some(gen_move) = self.parse_move(mv) {
+                self.position.make_move(gen_move);
             }
         }
 
         Ok(())
     }
+
+    /**
+     * Returns the legal move in the current position matching a move like "e2e4"
+     * */
+    fn parse_move(&mut self, mv: &str) -> Option<Move> {
+        let side = self.position.state.current_turn();
+        let moves = self
+            .move_gen
+            .generate_legal_moves(&mut self.position, side, MoveType::All);
+
+        (0..moves.len())
+            .map(|j| moves.get(j))
+            .find(|gen_move| mv == gen_move.to_string())
+    }
 }
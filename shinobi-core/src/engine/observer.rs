@@ -0,0 +1,103 @@
+use crate::mov::Move;
+use crate::pv::{PvLine, SearchResult};
+use crate::search::uci_score;
+
+/**
+ * State of the search after a completed iteration
+ * */
+#[derive(Debug, Clone)]
+pub struct SearchProgress<'a> {
+    pub depth: i32,
+    pub seldepth: i32,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time: i32,
+    pub hashfull: usize,
+
+    /// MultiPV lines sorted by score
+    pub lines: &'a [PvLine],
+}
+
+/**
+ * Receives updates from a running search, every method does nothing by default
+ * */
+pub trait SearchObserver {
+    fn on_iteration(&mut self, _progress: &SearchProgress) {}
+
+    /// Called for every root move searched once the search has run for a while
+    fn on_root_move(&mut self, _depth: i32, _mv: Move, _number: usize) {}
+
+    /// Called once with the result of the search
+    fn on_finish(&mut self, _result: &SearchResult) {}
+}
+
+/**
+ * Prints the search to stdout as UCI info and bestmove lines
+ * */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UciObserver;
+
+impl UciObserver {
+    fn print_pv(moves: &[Move]) {
+        print!(" pv");
+        for mv in moves.iter() {
+            print!(" {}", mv);
+        }
+        println!();
+    }
+}
+
+impl SearchObserver for UciObserver {
+    fn on_iteration(&mut self, progress: &SearchProgress) {
+        for (k, line) in progress.lines.iter().enumerate() {
+            print!(
+                "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} tbhits 0 time {}",
+                line.depth,
+                line.seldepth,
+                k + 1,
+                uci_score(line.score),
+                progress.nodes,
+                progress.nps,
+                progress.hashfull,
+                progress.time
+            );
+            Self::print_pv(&line.moves);
+        }
+    }
+
+    fn on_root_move(&mut self, depth: i32, mv: Move, number: usize) {
+        println!(
+            "info depth {} currmove {} currmovenumber {}",
+            depth, mv, number
+        );
+    }
+
+    fn on_finish(&mut self, result: &SearchResult) {
+        // No legal moves, UCI still expects a bestmove
+        let best_move = match result.best_move {
+            Some(best_move) => best_move,
+            None => {
+                println!("bestmove 0000");
+                return;
+            }
+        };
+
+        // Final totals including an unfinished iteration, matching the best move
+        print!(
+            "info depth {} seldepth {} multipv 1 score {} nodes {} nps {} hashfull {} tbhits 0 time {}",
+            result.depth,
+            result.seldepth,
+            uci_score(result.score),
+            result.nodes,
+            result.nps(),
+            result.hashfull,
+            result.time
+        );
+        Self::print_pv(&result.pv);
+
+        match result.ponder {
+            Some(ponder) => println!("bestmove {} ponder {}", best_move, ponder),
+            None => println!("bestmove {}", best_move),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::skill::{MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+
+/**
+ * Search parameters that can be tuned through UCI options
+ * */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchParams {
+    // Null move pruning
+    pub null_move: bool,
+    pub null_move_min_depth: i32,
+    pub null_move_reduction: i32,
+    pub null_move_verify_depth: i32,
+
+    // Reverse futility pruning
+    pub reverse_futility: bool,
+    pub reverse_futility_depth: i32,
+    pub reverse_futility_margin: i32,
+
+    // Late move reductions, base and divisor are in hundredths
+    pub late_move_reductions: bool,
+    pub lmr_min_depth: i32,
+    pub lmr_min_moves: i32,
+    pub lmr_base: i32,
+    pub lmr_divisor: i32,
+
+    // Late move pruning
+    pub late_move_pruning: bool,
+    pub lmp_depth: i32,
+    pub lmp_base: i32,
+
+    // Draw score in centipawns for the side to move at the root, positive avoids draws
+    pub contempt: i32,
+
+    // Clears the hash table and move ordering tables before every search, so equal node
+    // limited searches always return the same move and node count
+    pub deterministic: bool,
+
+    // Weaker play, see Skill
+    pub skill_level: i32,
+    pub limit_strength: bool,
+    pub elo: i32,
+}
+
+const CHECK_OPTIONS: [&str; 6] = [
+    "NullMove",
+    "ReverseFutility",
+    "LateMoveReductions",
+    "LateMovePruning",
+    "Deterministic",
+    "UCI_LimitStrength",
+];
+
+/// (name, min, max) of every spin option
+const SPIN_OPTIONS: [(&str, i32, i32); 14] = [
+    ("NullMoveMinDepth", 1, 16),
+    ("NullMoveReduction", 1, 8),
+    ("NullMoveVerifyDepth", 1, 64),
+    ("ReverseFutilityDepth", 0, 16),
+    ("ReverseFutilityMargin", 0, 1000),
+    ("LmrMinDepth", 1, 16),
+    ("LmrMinMoves", 1, 64),
+    ("LmrBase", 0, 300),
+    ("LmrDivisor", 100, 1000),
+    ("LmpDepth", 0, 16),
+    ("LmpBase", 0, 64),
+    ("Contempt", -200, 200),
+    ("Skill Level", 0, MAX_SKILL_LEVEL),
+    ("UCI_Elo", MIN_ELO, MAX_ELO),
+];
+
+impl SearchParams {
+    pub fn new() -> SearchParams {
+        SearchParams {
+            null_move: true,
+            null_move_min_depth: 3,
+            null_move_reduction: 3,
+            null_move_verify_depth: 12,
+            reverse_futility: true,
+            reverse_futility_depth: 6,
+            reverse_futility_margin: 80,
+            late_move_reductions: true,
+            lmr_min_depth: 3,
+            lmr_min_moves: 3,
+            lmr_base: 75,
+            lmr_divisor: 225,
+            late_move_pruning: true,
+            lmp_depth: 3,
+            lmp_base: 3,
+            contempt: 0,
+            deterministic: false,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+        }
+    }
+
+    /**
+     * Returns the "option name ..." lines sent in reply to "uci"
+     * */
+    pub fn uci_options() -> Vec<String> {
+        let mut defaults = SearchParams::new();
+        let mut options = Vec::new();
+
+        for name in CHECK_OPTIONS {
+            let default = *defaults.check_mut(name).unwrap();
+            options.push(format!(
+                "option name {} type check default {}",
+                name, default
+            ));
+        }
+
+        for (name, min, max) in SPIN_OPTIONS {
+            let default = *defaults.spin_mut(name).unwrap();
+            options.push(format!(
+                "option name {} type spin default {} min {} max {}",
+                name, default, min, max
+            ));
+        }
+
+        options
+    }
+
+    /**
+     * Sets the parameter for UCI option `name`, names are case insensitive
+     * */
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if let Some(name) = CHECK_OPTIONS
+            .iter()
+            .find(|option| option.eq_ignore_ascii_case(name))
+        {
+            let value = match value.to_lowercase().as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("Invalid {} value: {}", name, value)),
+            };
+
+            *self.check_mut(name).unwrap() = value;
+            return Ok(());
+        }
+
+        if let Some((name, min, max)) = SPIN_OPTIONS
+            .iter()
+            .find(|(option, ..)| option.eq_ignore_ascii_case(name))
+        {
+            let value = match value.parse::<i32>() {
+                Ok(v) => v.clamp(*min, *max),
+                Err(_) => return Err(format!("Invalid {} value: {}", name, value)),
+            };
+
+            *self.spin_mut(name).unwrap() = value;
+            return Ok(());
+        }
+
+        Err(format!("Unknown option: {}", name))
+    }
+
+    fn check_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "NullMove" => Some(&mut self.null_move),
+            "ReverseFutility" => Some(&mut self.reverse_futility),
+            "LateMoveReductions" => Some(&mut self.late_move_reductions),
+            "LateMovePruning" => Some(&mut self.late_move_pruning),
+            "Deterministic" => Some(&mut self.deterministic),
+            "UCI_LimitStrength" => Some(&mut self.limit_strength),
+            _ => None,
+        }
+    }
+
+    fn spin_mut(&mut self, name: &str) -> Option<&mut i32> {
+        match name {
+            "NullMoveMinDepth" => Some(&mut self.null_move_min_depth),
+            "NullMoveReduction" => Some(&mut self.null_move_reduction),
+            "NullMoveVerifyDepth" => Some(&mut self.null_move_verify_depth),
+            "ReverseFutilityDepth" => Some(&mut self.reverse_futility_depth),
+            "ReverseFutilityMargin" => Some(&mut self.reverse_futility_margin),
+            "LmrMinDepth" => Some(&mut self.lmr_min_depth),
+            "LmrMinMoves" => Some(&mut self.lmr_min_moves),
+            "LmrBase" => Some(&mut self.lmr_base),
+            "LmrDivisor" => Some(&mut self.lmr_divisor),
+            "LmpDepth" => Some(&mut self.lmp_depth),
+            "LmpBase" => Some(&mut self.lmp_base),
+            "Contempt" => Some(&mut self.contempt),
+            "Skill Level" => Some(&mut self.skill_level),
+            "UCI_Elo" => Some(&mut self.elo),
+            _ => None,
+        }
+    }
+
+    /**
+     * Logarithmic late move reduction for the nth move searched at depth
+     * */
+    pub fn lmr_reduction(&self, depth: i32, moves_searched: i32) -> i32 {
+        let reduction = self.lmr_base as f64 / 100.0
+            + (depth as f64).ln() * (moves_searched as f64).ln()
+                / (self.lmr_divisor as f64 / 100.0);
+
+        reduction as i32
+    }
+
+    /**
+     * Number of quiet moves searched at depth before the rest are pruned
+     * */
+    pub fn lmp_move_count(&self, depth: i32) -> i32 {
+        self.lmp_base + depth * depth
+    }
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,311 @@
+use crate::eval::{Param, Score, Tracer};
+use crate::{adjacent_files, get_file, get_rank};
+use crate::{BitBoard, Piece, Position, Side, Square, EMPTY_BITBOARD};
+
+const PAWN_TABLE_SIZE: usize = 0x4000;
+
+// Indexed by the rank of the pawn seen from its own side
+pub const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 17),
+    Score::new(15, 25),
+    Score::new(35, 55),
+    Score::new(65, 100),
+    Score::new(110, 160),
+    Score::new(0, 0),
+];
+
+pub const CONNECTED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(4, 2),
+    Score::new(7, 4),
+    Score::new(10, 8),
+    Score::new(20, 16),
+    Score::new(35, 30),
+    Score::new(55, 55),
+    Score::new(0, 0),
+];
+
+pub const ISOLATED_PAWN: Score = Score::new(-10, -15);
+pub const DOUBLED_PAWN: Score = Score::new(-10, -25);
+pub const BACKWARD_PAWN: Score = Score::new(-8, -12);
+
+// Endgame bonus per square of king distance to the square in front of a passed pawn,
+// multiplied by how far the pawn is past its fourth rank
+pub const PASSED_THEIR_KING_DISTANCE: i32 = 5;
+pub const PASSED_OUR_KING_DISTANCE: i32 = 2;
+
+// A pawn the enemy king can not catch with no enemy pieces left is almost a queen
+pub const UNSTOPPABLE_PASSED_PAWN: Score = Score::new(0, 700);
+
+/**
+ * Pawn structure of a position, the same for every position with the same pawns
+ * */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub key: u64,
+
+    /// White minus Black
+    pub score: Score,
+
+    /// Passed pawns of each side
+    pub passed: [BitBoard; 2],
+}
+
+/**
+ * Cache of pawn structure scores keyed by the pawn hash, owned by each clone of the search
+ * */
+#[derive(Debug, Clone)]
+pub struct PawnTable {
+    table: Vec<Option<PawnEntry>>,
+}
+
+impl PawnTable {
+    pub fn new() -> PawnTable {
+        PawnTable {
+            table: vec![None; PAWN_TABLE_SIZE],
+        }
+    }
+
+    /**
+     * Returns the pawn structure of position, evaluating and storing it on a miss
+     * */
+    pub fn probe(&mut self, position: &Position) -> PawnEntry {
+        let key = position.state.pawn_hash;
+        let index = key as usize % self.table.len();
+
+        match self.table[index] {
+            Some(entry) if entry.key == key => entry,
+            _ => {
+                let entry = evaluate_pawns(position);
+                self.table[index] = Some(entry);
+                entry
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry = None);
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+ * Passed, isolated, doubled, backward and connected pawns of both sides
+ * */
+pub fn evaluate_pawns(position: &Position) -> PawnEntry {
+    let (white, white_passed) = pawn_structure(position, Side::White, &mut ());
+    let (black, black_passed) = pawn_structure(position, Side::Black, &mut ());
+
+    PawnEntry {
+        key: position.state.pawn_hash,
+        score: white - black,
+        passed: [white_passed, black_passed],
+    }
+}
+
+/**
+ * Pawn structure score of side and its passed pawns
+ * */
+pub fn pawn_structure(
+    position: &Position,
+    side: Side,
+    tracer: &mut impl Tracer,
+) -> (Score, BitBoard) {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let ours = position.piece_bitboard(Piece::Pawn, side);
+    let theirs = position.piece_bitboard(Piece::Pawn, opponent);
+    let mut score = Score::default();
+    let mut passed = EMPTY_BITBOARD;
+
+    for square in ours {
+        let rank = relative_rank(side, square);
+        let forward = forward_ranks(side, square);
+        let neighbours = ours & adjacent_files(square);
+
+        if theirs & (get_file(square) | adjacent_files(square)) & forward == EMPTY_BITBOARD {
+            score += PASSED_PAWN[rank];
+            tracer.add(Param::PassedPawn(rank), side, 1.0);
+            passed.set_bit(square);
+        }
+
+        if neighbours == EMPTY_BITBOARD {
+            score += ISOLATED_PAWN;
+            tracer.add(Param::IsolatedPawn, side, 1.0);
+        } else if neighbours & !forward == EMPTY_BITBOARD && stop_attacked(side, square, theirs) {
+            // Every neighbour has gone ahead and the pawn can not advance to join them
+            score += BACKWARD_PAWN;
+            tracer.add(Param::BackwardPawn, side, 1.0);
+        }
+
+        if ours & get_file(square) & forward != EMPTY_BITBOARD {
+            score += DOUBLED_PAWN;
+            tracer.add(Param::DoubledPawn, side, 1.0);
+        }
+
+        let phalanx = neighbours & get_rank(square);
+        let supported = neighbours & behind_rank(side, square);
+        if phalanx | supported != EMPTY_BITBOARD {
+            score += CONNECTED_PAWN[rank];
+            tracer.add(Param::ConnectedPawn(rank), side, 1.0);
+        }
+    }
+
+    (score, passed)
+}
+
+/**
+ * Passed pawn terms that depend on more than the pawns, White minus Black
+ * */
+pub fn evaluate_passed_pawns(position: &Position, entry: &PawnEntry) -> Score {
+    let [white, black] = entry.passed;
+    passed_pawns(position, Side::White, white, &mut ())
+        - passed_pawns(position, Side::Black, black, &mut ())
+}
+
+/**
+ * King distances to the square in front of each passed pawn of side and whether the enemy
+ * king can still catch it
+ * */
+pub fn passed_pawns(
+    position: &Position,
+    side: Side,
+    passed: BitBoard,
+    tracer: &mut impl Tracer,
+) -> Score {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let our_king = position.king(side) as i32;
+    let their_king = position.king(opponent) as i32;
+    let mut score = Score::default();
+
+    for square in passed {
+        let rank = relative_rank(side, square) as i32;
+        let block = match side {
+            Side::White => square as i32 + 8,
+            Side::Black => square as i32 - 8,
+        };
+
+        let weight = (rank - 3).max(0);
+        let their_distance = weight * distance(their_king, block);
+        let our_distance = weight * distance(our_king, block);
+        score.eg +=
+            PASSED_THEIR_KING_DISTANCE * their_distance - PASSED_OUR_KING_DISTANCE * our_distance;
+        tracer.add(Param::PassedTheirKingDistance, side, their_distance as f64);
+        tracer.add(Param::PassedOurKingDistance, side, -our_distance as f64);
+
+        if unstoppable(position, side, square) {
+            score += UNSTOPPABLE_PASSED_PAWN;
+            tracer.add(Param::UnstoppablePassedPawn, side, 1.0);
+        }
+    }
+
+    score
+}
+
+/**
+ * Rule of the square, the pawn promotes before the enemy king reaches its promotion square
+ * and the enemy has no pieces left to stop it
+ * */
+fn unstoppable(position: &Position, side: Side, square: Square) -> bool {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+
+    if position.has_non_pawn_material(opponent) {
+        return false;
+    }
+
+    // Pieces of either side in the way slow the pawn down
+    let path = get_file(square) & forward_ranks(side, square);
+    if path & position.main_bitboard != EMPTY_BITBOARD {
+        return false;
+    }
+
+    let file = square as i32 % 8;
+    let promotion = match side {
+        Side::White => 56 + file,
+        Side::Black => file,
+    };
+
+    // The double push saves a move from the second rank
+    let pawn_distance = (7 - relative_rank(side, square) as i32).min(5);
+    let tempo = (position.state.current_turn() == opponent) as i32;
+
+    distance(position.king(opponent) as i32, promotion) - tempo > pawn_distance
+}
+
+/**
+ * The stop square of the pawn is attacked by an enemy pawn
+ * */
+fn stop_attacked(side: Side, square: Square, theirs: BitBoard) -> bool {
+    let rank = square as i32 / 8;
+    let attacker_rank = match side {
+        Side::White => rank + 2,
+        Side::Black => rank - 2,
+    };
+
+    if !(0..8).contains(&attacker_rank) {
+        return false;
+    }
+
+    let attacker_square = Square::from((attacker_rank * 8 + square as i32 % 8) as u64);
+    theirs & adjacent_files(square) & get_rank(attacker_square) != EMPTY_BITBOARD
+}
+
+/**
+ * Rank of square counted from the back rank of side, 0 to 7
+ * */
+pub fn relative_rank(side: Side, square: Square) -> usize {
+    match side {
+        Side::White => square as usize / 8,
+        Side::Black => 7 - square as usize / 8,
+    }
+}
+
+/**
+ * Every square on the ranks in front of square as seen by side
+ * */
+pub fn forward_ranks(side: Side, square: Square) -> BitBoard {
+    let rank = square as u32 / 8;
+    match side {
+        Side::White => BitBoard(u64::MAX.checked_shl(8 * (rank + 1)).unwrap_or(0)),
+        Side::Black => BitBoard((1u64 << (8 * rank)) - 1),
+    }
+}
+
+/**
+ * The rank just behind square as seen by side, empty on the back rank
+ * */
+fn behind_rank(side: Side, square: Square) -> BitBoard {
+    let rank = square as i32 / 8;
+    let behind = match side {
+        Side::White => rank - 1,
+        Side::Black => rank + 1,
+    };
+
+    if (0..8).contains(&behind) {
+        BitBoard(0xFF << (8 * behind))
+    } else {
+        EMPTY_BITBOARD
+    }
+}
+
+/**
+ * King moves between two squares
+ * */
+fn distance(a: i32, b: i32) -> i32 {
+    ((a % 8) - (b % 8)).abs().max(((a / 8) - (b / 8)).abs())
+}
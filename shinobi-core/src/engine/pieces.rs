@@ -0,0 +1,261 @@
+use crate::eval::{Param, Score, Tracer};
+use crate::pawns::{forward_ranks, relative_rank};
+use crate::{adjacent_files, get_file, get_rank};
+use crate::{BitBoard, MoveGenerator, Piece, Position, Side, Square, EMPTY_BITBOARD};
+
+// Per square a piece can move to, counted from the usual number of squares of that piece
+pub const MOBILITY: [Score; 6] = [
+    Score::new(0, 0),
+    Score::new(4, 4),
+    Score::new(5, 5),
+    Score::new(2, 4),
+    Score::new(1, 2),
+    Score::new(0, 0),
+];
+const MOBILITY_BASE: [i32; 6] = [0, 4, 7, 7, 14, 0];
+
+// Danger of each attack on a square next to the enemy king
+pub const KING_ATTACK_WEIGHTS: [i32; 6] = [0, 20, 20, 40, 80, 0];
+
+// Percentage of the attack weights counted, by the number of pieces attacking the king zone,
+// a lone attacker is no real threat
+const KING_ATTACKERS_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+
+pub const PAWN_SHIELD: Score = Score::new(12, 0);
+pub const KING_SEMI_OPEN_FILE: Score = Score::new(-15, 0);
+pub const KING_OPEN_FILE: Score = Score::new(-25, 0);
+
+pub const ROOK_OPEN_FILE: Score = Score::new(25, 10);
+pub const ROOK_SEMI_OPEN_FILE: Score = Score::new(12, 6);
+pub const ROOK_SEVENTH_RANK: Score = Score::new(20, 30);
+
+pub const KNIGHT_OUTPOST: Score = Score::new(25, 15);
+pub const BISHOP_PAIR: Score = Score::new(30, 50);
+
+/**
+ * Squares side can move its pieces to, leaving out its own pieces and squares covered by
+ * enemy pawns
+ * */
+pub fn mobility(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    side: Side,
+    tracer: &mut impl Tracer,
+) -> Score {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let area =
+        !position.side_bitboards[side as usize] & !pawn_attacks(position, move_gen, opponent);
+    let mut score = Score::default();
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in position.piece_bitboard(piece, side) {
+            let moves = (piece_attacks(position, move_gen, piece, square) & area)
+                .0
+                .count_ones();
+            let gain = moves as i32 - MOBILITY_BASE[piece as usize];
+
+            score += MOBILITY[piece as usize] * gain;
+            tracer.add(Param::Mobility(piece), side, gain as f64);
+        }
+    }
+
+    score
+}
+
+/**
+ * Pawn shield and open files in front of the king of side, and the pieces attacking the
+ * squares around it
+ * */
+pub fn king_safety(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    side: Side,
+    tracer: &mut impl Tracer,
+) -> Score {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let king = position.king(side);
+    let our_pawns = position.piece_bitboard(Piece::Pawn, side);
+    let their_pawns = position.piece_bitboard(Piece::Pawn, opponent);
+    let mut score = Score::default();
+
+    // Only a castled or uncastled king still on its first two ranks has a shield
+    if relative_rank(side, king) <= 1 {
+        let front = forward_ranks(side, king) & !forward_ranks(side, shift_forward(side, king, 2));
+        let shield = our_pawns & front & (get_file(king) | adjacent_files(king));
+        score += PAWN_SHIELD * shield.0.count_ones() as i32;
+        tracer.add(Param::PawnShield, side, shield.0.count_ones() as f64);
+    }
+
+    // One square on the rank of the king for each file next to it
+    let files = (get_file(king) | adjacent_files(king)) & get_rank(king);
+    for square in files {
+        let file = get_file(square);
+        if file & our_pawns != EMPTY_BITBOARD {
+            continue;
+        }
+
+        if file & their_pawns == EMPTY_BITBOARD {
+            score += KING_OPEN_FILE;
+            tracer.add(Param::KingOpenFile, side, 1.0);
+        } else {
+            score += KING_SEMI_OPEN_FILE;
+            tracer.add(Param::KingSemiOpenFile, side, 1.0);
+        }
+    }
+
+    let zone = move_gen.king_moves[king as usize] | BitBoard(1 << king as u64);
+    let mut attackers = 0;
+
+    // Attacks on the zone by each kind of piece
+    let mut attacks = [0; 6];
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in position.piece_bitboard(piece, opponent) {
+            let zone_attacks = piece_attacks(position, move_gen, piece, square) & zone;
+            if zone_attacks != EMPTY_BITBOARD {
+                attackers += 1;
+                attacks[piece as usize] += zone_attacks.0.count_ones() as i32;
+            }
+        }
+    }
+
+    let scale = KING_ATTACKERS_SCALE[attackers.min(KING_ATTACKERS_SCALE.len() - 1)];
+    let mut weight = 0;
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        weight += KING_ATTACK_WEIGHTS[piece as usize] * attacks[piece as usize];
+        let count = -(attacks[piece as usize] * scale) as f64 / 100.0;
+        tracer.add(Param::KingAttack(piece), side, count);
+    }
+    score.mg -= weight * scale / 100;
+
+    score
+}
+
+/**
+ * Rooks of side on open and semi-open files and on the seventh rank
+ * */
+pub fn rooks(position: &Position, side: Side, tracer: &mut impl Tracer) -> Score {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let our_pawns = position.piece_bitboard(Piece::Pawn, side);
+    let their_pawns = position.piece_bitboard(Piece::Pawn, opponent);
+    let mut score = Score::default();
+
+    for square in position.piece_bitboard(Piece::Rook, side) {
+        let file = get_file(square);
+        if file & our_pawns == EMPTY_BITBOARD {
+            if file & their_pawns == EMPTY_BITBOARD {
+                score += ROOK_OPEN_FILE;
+                tracer.add(Param::RookOpenFile, side, 1.0);
+            } else {
+                score += ROOK_SEMI_OPEN_FILE;
+                tracer.add(Param::RookSemiOpenFile, side, 1.0);
+            }
+        }
+
+        // Only worth it with pawns to eat there or the enemy king cut off on its back rank
+        if relative_rank(side, square) == 6 {
+            let seventh = their_pawns
+                .into_iter()
+                .any(|pawn| relative_rank(side, pawn) == 6);
+            if seventh || relative_rank(side, position.king(opponent)) == 7 {
+                score += ROOK_SEVENTH_RANK;
+                tracer.add(Param::RookSeventhRank, side, 1.0);
+            }
+        }
+    }
+
+    score
+}
+
+/**
+ * Knights of side in the enemy half, supported by a pawn and out of reach of enemy pawns
+ * */
+pub fn outposts(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    side: Side,
+    tracer: &mut impl Tracer,
+) -> Score {
+    let opponent = match side {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    let our_pawns = position.piece_bitboard(Piece::Pawn, side);
+    let their_pawns = position.piece_bitboard(Piece::Pawn, opponent);
+    let mut score = Score::default();
+
+    for square in position.piece_bitboard(Piece::Knight, side) {
+        if !(3..=5).contains(&relative_rank(side, square)) {
+            continue;
+        }
+
+        // Our pawns defending the square are those an enemy pawn there would attack
+        let supported =
+            move_gen.pawn_attacks[opponent as usize][square as usize] & our_pawns != EMPTY_BITBOARD;
+        let chased = their_pawns & adjacent_files(square) & forward_ranks(side, square);
+
+        if supported && chased == EMPTY_BITBOARD {
+            score += KNIGHT_OUTPOST;
+            tracer.add(Param::KnightOutpost, side, 1.0);
+        }
+    }
+
+    score
+}
+
+pub fn bishop_pair(position: &Position, side: Side, tracer: &mut impl Tracer) -> Score {
+    if position.piece_bitboard(Piece::Bishop, side).0.count_ones() >= 2 {
+        tracer.add(Param::BishopPair, side, 1.0);
+        BISHOP_PAIR
+    } else {
+        Score::default()
+    }
+}
+
+fn piece_attacks(
+    position: &Position,
+    move_gen: &MoveGenerator,
+    piece: Piece,
+    square: Square,
+) -> BitBoard {
+    let occupancy = position.main_bitboard;
+    match piece {
+        Piece::Knight => move_gen.knight_moves[square as usize],
+        Piece::Bishop => move_gen.get_bishop_moves(square as u64, occupancy),
+        Piece::Rook => move_gen.get_rook_moves(square as u64, occupancy),
+        Piece::Queen => move_gen.get_queen_moves(square as u64, occupancy),
+        Piece::King => move_gen.king_moves[square as usize],
+        // Pawn attacks depend on the side, see pawn_attacks
+        Piece::Pawn => EMPTY_BITBOARD,
+    }
+}
+
+fn pawn_attacks(position: &Position, move_gen: &MoveGenerator, side: Side) -> BitBoard {
+    let mut attacks = EMPTY_BITBOARD;
+    for square in position.piece_bitboard(Piece::Pawn, side) {
+        attacks |= move_gen.pawn_attacks[side as usize][square as usize];
+    }
+
+    attacks
+}
+
+/**
+ * The square ranks ahead of square as seen by side, clamped to the board
+ * */
+fn shift_forward(side: Side, square: Square, ranks: i32) -> Square {
+    let rank = match side {
+        Side::White => (square as i32 / 8 + ranks).min(7),
+        Side::Black => (square as i32 / 8 - ranks).max(0),
+    };
+
+    Square::from((rank * 8 + square as i32 % 8) as u64)
+}
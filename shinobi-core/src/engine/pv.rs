@@ -14,4 +14,62 @@ impl PvInfo {
             pv_length: [0; PV_SIZE],
         }
     }
+
+    /**
+     * Returns the principal variation found from the root
+     * */
+    pub fn root_line(&self) -> Vec<Move> {
+        self.pv_table[0][0..self.pv_length[0] as usize]
+            .iter()
+            .map_while(|mv| *mv)
+            .collect()
+    }
+}
+
+impl Default for PvInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+ * A principal variation and its score as reported for one MultiPV slot
+ * */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: i32,
+    pub depth: i32,
+    pub seldepth: i32,
+    pub moves: Vec<Move>,
+}
+
+impl PvLine {
+    pub fn best_move(&self) -> Option<Move> {
+        self.moves.first().copied()
+    }
+}
+
+/**
+ * Outcome of `Search::go`, time is in milliseconds
+ * */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+    pub score: i32,
+    pub pv: Vec<Move>,
+    pub depth: i32,
+    pub seldepth: i32,
+    pub nodes: u64,
+    pub time: i32,
+    pub hashfull: usize,
+
+    /// Every MultiPV line sorted by score, the first one is the PV above
+    pub lines: Vec<PvLine>,
+}
+
+impl SearchResult {
+    pub fn nps(&self) -> u64 {
+        self.nodes * 1000 / self.time.max(1) as u64
+    }
 }
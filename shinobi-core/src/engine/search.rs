@@ -1,146 +1,145 @@
+use crate::eval;
+use crate::evaluator::{ClassicalEvaluator, Evaluator};
 use crate::get_time_ms;
+use crate::limits::SearchLimits;
 use crate::mov::Move;
 use crate::mov::MoveList;
 use crate::mov::MoveType;
 use crate::mov::NULL_MOVE;
-use crate::pv::PvInfo;
+use crate::observer::{SearchObserver, SearchProgress};
+use crate::params::SearchParams;
+use crate::pv::{PvInfo, PvLine, SearchResult};
+use crate::see::SEE_VALUES;
+use crate::skill::Skill;
+use crate::tt::{Entry, HASH_FLAG_ALPHA, HASH_FLAG_BETA, HASH_FLAG_EXACT, TT};
 use crate::MoveGenerator;
 use crate::Piece;
 use crate::Position;
 use crate::SearchInfo;
 use crate::Side;
+use crate::Square;
 use crate::EMPTY_BITBOARD;
+use crate::{SECOND_RANK, SEVENTH_RANK};
 
 use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::cmp::Reverse;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use strum::IntoEnumIterator;
+use std::sync::Mutex;
 
-const WEIGHTS: [i32; 6] = [100, 320, 330, 500, 900, 20000];
-const LARGE_NUM: i32 = 30000;
+pub(crate) const LARGE_NUM: i32 = 30000;
 const MATE: i32 = 29000;
 pub const MAX_DEPTH: i32 = 7;
-pub static mut BEST_MOVE: Option<Move> = None;
-
-// PIECE SQUARE TABLES
-//
-// PSQT FORMAT
-/*
- * A8  B8  C8  D8  E8  F8  G8  H8
- * A7  B7  C7  D7  E7  F7  G7  H7
- * A6  B6  C6  D6  E6  F6  G6  H6
- * A5  B5  C5  D5  E5  F5  G5  H5
- * A4  B4  C4  D4  E4  F4  G4  H4
- * A3  B3  C3  D3  E3  F3  G3  H3
- * A2  B2  C2  D2  E2  F2  G2  H2
- * A1  B1  C1  D1  E1  F1  G1  H1
- */
-
-#[rustfmt::skip]
-const FLIP: [usize; 64]  = [
-    56,  57,  58,  59,  60,  61,  62,  63,
-    48,  49,  50,  51,  52,  53,  54,  55,
-    40,  41,  42,  43,  44,  45,  46,  47,
-    32,  33,  34,  35,  36,  37,  38,  39,
-    24,  25,  26,  27,  28,  29,  30,  31,
-    16,  17,  18,  19,  20,  21,  22,  23,
-    8,   9,   10,  11,  12,  13,  14,  15,
-    0,   1,   2,   3,   4,   5,   6,   7
-];
+const MAX_PLY: i32 = 64;
+const MATE_BOUND: i32 = MATE - MAX_PLY;
 
-#[rustfmt::skip]
-const PAWNS_SQ: [i32; 64] = [
-     0,  0,  0,    0,   0,   0,  0,  0,
-    50, 50,  50,  50,  50,  50, 50, 50,
-    10, 10,  20,  30,  30,  20, 10, 10,
-     5,  5,  10,  25,  25,  10,  5,  5,
-     0,  0,   0,  20,  20,   0,  0,  0,
-     5, -5, -10,   0,   0, -10, -5,  5,
-     5, 10,  10, -20, -20,  10, 10,  5,
-     0,  0,   0,   0,   0,   0,  0,  0,
-];
+// Every multiple of this in the history score reduces a late move by one ply less
+const HISTORY_REDUCTION_DIVISOR: i32 = 8192;
 
-#[rustfmt::skip]
-const KNIGHT_SQ: [i32; 64] = [
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    -40, -20,   0,   0,   0,   0, -20, -40,
-    -30,   0,  10,  15,  15,  10,   0, -30,
-    -30,   5,  15,  20,  20,  15,   5, -30,
-    -30,   0,  15,  20,  20,  15,   0, -30,
-    -30,   5,  10,  15,  15,  10,   5, -30,
-    -40, -20,   0,   5,   5,   0, -20, -40,
-    -50, -40, -30, -30, -30, -30, -40, -50,
-];
+// Singular extensions are only tried this deep, with a TT entry at most this much shallower
+const SINGULAR_DEPTH: i32 = 6;
+const SINGULAR_TT_DEPTH_MARGIN: i32 = 3;
 
-#[rustfmt::skip]
-const BISHOP_SQ: [i32; 64] = [
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -10,   0,   5,  10,  10,   5,   0, -10,
-    -10,   5,   5,  10,  10,   5,   5, -10,
-    -10,   0,  10,  10,  10,  10,   0, -10,
-    -10,  10,  10,  10,  10,  10,  10, -10,
-    -10,   5,   0,   0,   0,   0,   5, -10,
-    -20, -10, -10, -10, -10, -10, -10, -20,
-];
+// Root moves are only reported with currmove once the search has run this long
+const CURRMOVE_DELAY_MS: i32 = 3000;
 
-#[rustfmt::skip]
-const ROOK_SQ: [i32; 64] = [
-     0,  0,  0,  0,  0,  0,  0,  0,
-     5, 10, 10, 10, 10, 10, 10,  5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-    -5,  0,  0,  0,  0,  0,  0, -5,
-     0,  0,  0,  5,  5,  0,  0,  0,
-];
+// Null moves need at least a rook or two minor pieces, see eval::PHASE_WEIGHTS
+const NULL_MOVE_MIN_PHASE: i32 = 2;
 
-#[rustfmt::skip]
-const QUEEN_SQ: [i32; 64] = [
-    -20, -10, -10, -5, -5, -10, -10, -20,
-    -10,   0,   0,  0,  0,   0,   0, -10,
-    -10,   0,   5,  5,  5,   5,   0, -10,
-     -5,   0,   5,  5,  5,   5,   0,  -5,
-      0,   0,   5,  5,  5,   5,   0,  -5,
-    -10,   5,   5,  5,  5,   5,   0, -10,
-    -10,   0,   5,  0,  0,   0,   0, -10,
-    -20, -10, -10, -5, -5, -10, -10, -20,
-];
+// Aspiration windows
+const ASPIRATION_DEPTH: i32 = 4;
+const ASPIRATION_WINDOW: i32 = 30;
 
-#[rustfmt::skip]
-const KING_SQ: [i32; 64] = [
-  -30, -40, -40, -50, -50, -40, -40, -30,
-  -30, -40, -40, -50, -50, -40, -40, -30,
-  -30, -40, -40, -50, -50, -40, -40, -30,
-  -30, -40, -40, -50, -50, -40, -40, -30,
-  -20, -30, -30, -40, -40, -30, -30, -20,
-  -10, -20, -20, -20, -20, -20, -20, -10, 
-   20,  20,   0,   0,   0,   0,  20,  20,
-   20,  30,  10,   0,   0,  10,  30,  20
-];
+// Quiescence skips captures that can not bring the score within this of alpha
+const DELTA_MARGIN: i32 = 200;
+pub const MAX_MULTI_PV: usize = 64;
+pub static mut BEST_MOVE: Option<Move> = None;
 
 // MVV_VLA[victim][attacker]
 const MVV_LVA: [[i32; 6]; 6] = [
-    [105, 205, 305, 405, 505, 605],
-    [104, 204, 304, 404, 504, 604],
-    [103, 203, 303, 403, 503, 603],
-    [102, 202, 302, 402, 502, 602],
-    [101, 201, 301, 401, 501, 601],
-    [100, 200, 300, 400, 500, 600],
+    [105, 104, 103, 102, 101, 100],
+    [205, 204, 203, 202, 201, 200],
+    [305, 304, 303, 302, 301, 300],
+    [405, 404, 403, 402, 401, 400],
+    [505, 504, 503, 502, 501, 500],
+    [605, 604, 603, 602, 601, 600],
 ];
 
+// Move ordering, captures first then killers, the counter move, quiets by history and
+// captures that lose material
+const CAPTURE_SCORE: i32 = 1_000_000;
+const FIRST_KILLER_SCORE: i32 = 900_000;
+const SECOND_KILLER_SCORE: i32 = 800_000;
+const COUNTER_MOVE_SCORE: i32 = 700_000;
+const LOSING_CAPTURE_SCORE: i32 = -CAPTURE_SCORE / 2;
+const UNDER_PROMOTION_SCORE: i32 = -CAPTURE_SCORE;
+
+// History scores are kept within +-MAX_HISTORY by the gravity formula
+const MAX_HISTORY: i32 = 16384;
+const MAX_HISTORY_BONUS: i32 = 1200;
+
+/// Continuation history of one side, [prev piece][prev target][piece][target]
+/// Built a piece at a time on the heap, the whole table is too large for a thread's stack
+type ContinuationHistory = Vec<[[[i32; 64]; 6]; 64]>;
+
 #[derive(Clone)]
 pub struct Search {
     pub searching: Arc<AtomicBool>,
     pub depth: u8,
     pub ply: u8,
-    pub nodes: u32,
+    pub nodes: u64,
+
+    /// Deepest ply reached in the current iteration, quiescence included
+    pub seldepth: u8,
+
+    /// get_time_ms() when the search started
+    start_time: i32,
     pub best_move: Option<Move>,
     pub pv: PvInfo,
     pub killer_moves: [[Move; 64]; 2],
-    pub history_moves: [[[i32; 6]; 2]; 64],
+
+    /// Butterfly history, [side][from][target]
+    pub history_moves: [[[i32; 64]; 64]; 2],
+
+    /// Reply that refuted the previous move, [side][prev piece][prev target]
+    pub counter_moves: [[[Move; 64]; 6]; 2],
+
+    /// History of a move following the moves one and two plies earlier, per side
+    continuation_history: Vec<ContinuationHistory>,
+
+    /// Number of principal variations to search and report
+    pub multi_pv: usize,
+    pub pv_lines: Vec<PvLine>,
+
+    /// Root moves already reported in an earlier MultiPV slot
+    excluded_root_moves: Vec<Move>,
+
+    pub params: SearchParams,
+
+    /// Null moves are not tried before this ply while verifying a null move cutoff
+    nmp_min_ply: u8,
+
+    /// Shared by every clone of the search
+    pub tt: Arc<TT>,
+
+    /// Static evaluation, one per clone of the search
+    evaluator: Box<dyn Evaluator>,
+
+    /// Move skipped at each ply while testing the TT move for singularity
+    excluded_moves: [Move; MAX_PLY as usize],
+
+    /// Square captured on by the move made at each ply, used for recapture extensions
+    capture_squares: [Option<Square>; MAX_PLY as usize],
+
+    /// Extensions on the current path, limited to the iteration depth
+    path_extensions: i32,
+
+    /// Nodes searched before stopping, from the limits or the skill level
+    node_limit: Option<u64>,
+
+    /// Notified of the progress of every search, shared by every clone of the search
+    observer: Option<Arc<Mutex<dyn SearchObserver + Send>>>,
 }
 
 impl Serialize for Search {
@@ -148,16 +147,23 @@ impl Serialize for Search {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Search", 5)?;
+        let mut state = serializer.serialize_struct("Search", 6)?;
         state.serialize_field("searching", &self.searching.load(Ordering::Relaxed))?;
         state.serialize_field("depth", &self.depth)?;
         state.serialize_field("ply", &self.ply)?;
         state.serialize_field("nodes", &self.nodes)?;
         state.serialize_field("best_move", &self.best_move)?;
+        state.serialize_field("multi_pv", &self.multi_pv)?;
         state.end()
     }
 }
 
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Search {
     pub fn new() -> Search {
         Search {
@@ -165,10 +171,90 @@ impl Search {
             depth: 0,
             ply: 0,
             nodes: 0,
+            seldepth: 0,
+            start_time: 0,
             best_move: None,
             pv: PvInfo::new(),
             killer_moves: [[NULL_MOVE; 64]; 2],
-            history_moves: [[[0; 6]; 2]; 64],
+            history_moves: [[[0; 64]; 64]; 2],
+            counter_moves: [[[NULL_MOVE; 64]; 6]; 2],
+            continuation_history: vec![vec![[[[0; 64]; 6]; 64]; 6]; 2],
+            multi_pv: 1,
+            pv_lines: Vec::new(),
+            excluded_root_moves: Vec::new(),
+            params: SearchParams::new(),
+            nmp_min_ply: 0,
+            tt: Arc::new(TT::new()),
+            evaluator: Box::new(ClassicalEvaluator::new()),
+            excluded_moves: [NULL_MOVE; MAX_PLY as usize],
+            capture_squares: [None; MAX_PLY as usize],
+            path_extensions: 0,
+            node_limit: None,
+            observer: None,
+        }
+    }
+
+    /**
+     * Resets everything learned from previous searches while keeping the options
+     * */
+    pub fn clear(&mut self) {
+        self.depth = 0;
+        self.ply = 0;
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.best_move = None;
+        self.pv = PvInfo::new();
+        self.killer_moves = [[NULL_MOVE; 64]; 2];
+        self.history_moves = [[[0; 64]; 64]; 2];
+        self.counter_moves = [[[NULL_MOVE; 64]; 6]; 2];
+        self.continuation_history = vec![vec![[[[0; 64]; 6]; 64]; 6]; 2];
+        self.pv_lines.clear();
+        self.excluded_root_moves.clear();
+        self.nmp_min_ply = 0;
+        self.excluded_moves = [NULL_MOVE; MAX_PLY as usize];
+        self.capture_squares = [None; MAX_PLY as usize];
+        self.path_extensions = 0;
+        self.tt.clear();
+        self.evaluator.clear();
+    }
+
+    /**
+     * Searches `position` within `limits` and returns the result
+     *
+     * Library entry point, progress is reported to the observer set with `set_observer`
+     * */
+    pub fn go(
+        &mut self,
+        position: &Position,
+        move_gen: &MoveGenerator,
+        limits: SearchLimits,
+    ) -> SearchResult {
+        let mut info = SearchInfo::from_limits(limits, position.state.current_turn());
+        let mut position = position.clone();
+
+        self.searching.store(true, Ordering::Relaxed);
+        let result = self.search_position(&mut info, &mut position, move_gen);
+        self.searching.store(false, Ordering::Relaxed);
+
+        result
+    }
+
+    pub fn set_observer<O: SearchObserver + Send + 'static>(&mut self, observer: Arc<Mutex<O>>) {
+        self.observer = Some(observer);
+    }
+
+    /**
+     * Replaces the static evaluation, the classical evaluator is used by default
+     * */
+    pub fn set_evaluator<E: Evaluator + 'static>(&mut self, evaluator: E) {
+        self.evaluator = Box::new(evaluator);
+    }
+
+    fn notify(&self, event: impl FnOnce(&mut dyn SearchObserver)) {
+        if let Some(observer) = &self.observer {
+            if let Ok(mut observer) = observer.lock() {
+                event(&mut *observer);
+            }
         }
     }
 
@@ -177,50 +263,257 @@ impl Search {
         info: &mut SearchInfo,
         position: &mut Position,
         move_gen: &MoveGenerator,
-        depth: i32,
-    ) {
+    ) -> SearchResult {
+        // Without a depth the search goes as deep as the clock or the other limits allow,
+        // only a bare go has nothing else to stop it
+        let limited =
+            info.limits().infinite || info.limits().nodes.is_some() || info.stop_time.is_some();
+        let mut depth = match info.limits().depth {
+            Some(depth) => depth,
+            None if limited => MAX_PLY - 1,
+            None => MAX_DEPTH,
+        }
+        .clamp(1, MAX_PLY - 1);
+
+        // Nothing learned from earlier searches may change this one
+        if self.params.deterministic {
+            self.clear();
+        }
+
+        self.node_limit = info.limits().nodes;
+        let multi_pv = self.multi_pv;
+        let skill = Skill::from_params(&self.params);
+        if let Some(skill) = skill {
+            depth = depth.min(skill.max_depth());
+            let nodes = skill.max_nodes();
+            self.node_limit = Some(self.node_limit.map_or(nodes, |limit| limit.min(nodes)));
+            self.multi_pv = skill.multi_pv(multi_pv);
+        }
+
         // Reset
         self.ply = 0;
         self.nodes = 0;
         self.best_move = None;
+        self.pv_lines.clear();
+        self.start_time = info.start_time.unwrap_or_else(get_time_ms);
+        self.evaluator.on_new_search(position);
         log::info!("SEARCHED STARTED");
         let mut d = 1;
         loop {
-            let score = self.negamax(info, position, move_gen, -LARGE_NUM, LARGE_NUM, d);
-            if self.searching.load(Ordering::Relaxed) {
-                print!(
-                    "info score cp {} depth {} nodes {} pv",
-                    score, d, self.nodes
-                );
-                for count in 0..self.pv.pv_length[0] {
-                    print!(" {}", self.pv.pv_table[0][count as usize].unwrap());
+            let lines = self.search_lines(info, position, move_gen, d);
+            let mut mate_proven = false;
+            // Only a fully searched iteration replaces the previous lines
+            if self.searching.load(Ordering::Relaxed) && !info.stopped {
+                let time = self.elapsed();
+                let progress = SearchProgress {
+                    depth: d,
+                    seldepth: self.seldepth as i32,
+                    nodes: self.nodes,
+                    nps: self.nodes * 1000 / time.max(1) as u64,
+                    time,
+                    hashfull: self.tt.hashfull(),
+                    lines: &lines,
+                };
+                self.notify(|observer| observer.on_iteration(&progress));
+
+                if !lines.is_empty() {
+                    // Searching deeper can not change a mate found within the depth
+                    let score = lines[0].score.abs();
+                    mate_proven = score >= MATE_BOUND && MATE - score <= d;
+
+                    self.best_move = lines[0].best_move();
+                    self.pv_lines = lines;
                 }
             }
-            println!();
 
-            if d == depth {
+            if d >= depth || (mate_proven && !info.limits().infinite) {
                 break;
             }
 
-            if info.stopped {
+            // Stopped by a limit or from outside through the searching flag
+            if info.stopped || !self.searching.load(Ordering::Relaxed) {
                 break;
             }
             d += 1;
         }
 
-        if let Some(best_move) = self.best_move {
-            log::info!("BEST_MOVE: {:?} NODES: {}", best_move, self.nodes);
+        // An infinite search only reports its best move once stopped
+        while info.limits().infinite && self.searching.load(Ordering::Relaxed) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        self.multi_pv = multi_pv;
+        let mut result = self.result();
 
-            println!("bestmove {}", best_move);
+        // A weaker skill plays one of the other lines
+        if let Some(skill) = skill {
+            let deterministic = self.params.deterministic;
+            if let Some(line) = skill.pick(&result.lines, deterministic).cloned() {
+                result.best_move = line.best_move();
+                result.ponder = line.moves.get(1).copied();
+                result.score = line.score;
+                result.pv = line.moves;
+            }
+        }
+
+        // Stopped before the first iteration finished, any legal move beats none
+        if result.best_move.is_none() {
+            let side = position.state.current_turn();
+            let moves = move_gen.generate_legal_moves(position, side, MoveType::All);
+            result.best_move = (0..moves.len())
+                .map(|i| moves.get(i))
+                .find(|mv| info.search_moves().is_empty() || info.search_moves().contains(mv));
+            result.pv = result.best_move.into_iter().collect();
         }
+
+        log::info!("BEST_MOVE: {:?} NODES: {}", result.best_move, result.nodes);
+        self.notify(|observer| observer.on_finish(&result));
         log::info!("SEARCH ENDED");
+
+        result
+    }
+
+    fn result(&self) -> SearchResult {
+        let best = match self.pv_lines.first() {
+            Some(best) => best,
+            None => {
+                return SearchResult {
+                    nodes: self.nodes,
+                    time: self.elapsed(),
+                    hashfull: self.tt.hashfull(),
+                    ..SearchResult::default()
+                }
+            }
+        };
+
+        SearchResult {
+            best_move: best.best_move(),
+            ponder: best.moves.get(1).copied(),
+            score: best.score,
+            pv: best.moves.clone(),
+            depth: best.depth,
+            seldepth: best.seldepth,
+            nodes: self.nodes,
+            time: self.elapsed(),
+            hashfull: self.tt.hashfull(),
+            lines: self.pv_lines.clone(),
+        }
+    }
+
+    /**
+     * Runs one iteration for every MultiPV slot, excluding the root moves of the lines
+     * already found, and returns the lines sorted by score
+     * */
+    fn search_lines(
+        &mut self,
+        info: &mut SearchInfo,
+        position: &mut Position,
+        move_gen: &MoveGenerator,
+        depth: i32,
+    ) -> Vec<PvLine> {
+        let mut lines: Vec<PvLine> = Vec::with_capacity(self.multi_pv);
+        self.excluded_root_moves.clear();
+        self.depth = depth as u8;
+        self.seldepth = 0;
+
+        for slot in 0..self.multi_pv.max(1) {
+            let prev_score = self.pv_lines.get(slot).map(|line| line.score);
+            let score = self.aspiration_search(info, position, move_gen, depth, prev_score);
+
+            if !self.searching.load(Ordering::Relaxed) || info.stopped {
+                return Vec::new();
+            }
+
+            // Every root move has been searched already
+            let moves = self.pv.root_line();
+            if moves.is_empty() {
+                break;
+            }
+
+            self.excluded_root_moves.push(moves[0]);
+            lines.push(PvLine {
+                score,
+                depth,
+                seldepth: 0,
+                moves,
+            });
+        }
+
+        for line in lines.iter_mut() {
+            line.seldepth = self.seldepth as i32;
+        }
+
+        lines.sort_by_key(|line| Reverse(line.score));
+        lines
+    }
+
+    /**
+     * Milliseconds since the search started
+     * */
+    fn elapsed(&self) -> i32 {
+        get_time_ms().wrapping_sub(self.start_time).max(0)
+    }
+
+    /**
+     * Searches the root with a window around the score of the previous iteration,
+     * widening it until the score falls inside
+     * */
+    fn aspiration_search(
+        &mut self,
+        info: &mut SearchInfo,
+        position: &mut Position,
+        move_gen: &MoveGenerator,
+        depth: i32,
+        prev_score: Option<i32>,
+    ) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match prev_score {
+            Some(score) if depth >= ASPIRATION_DEPTH && score.abs() < MATE_BOUND => (
+                (score - delta).max(-LARGE_NUM),
+                (score + delta).min(LARGE_NUM),
+            ),
+            _ => (-LARGE_NUM, LARGE_NUM),
+        };
+
+        loop {
+            let score = self.negamax(info, position, move_gen, alpha, beta, depth);
+
+            if !self.searching.load(Ordering::Relaxed) || info.stopped {
+                return score;
+            }
+
+            if score <= alpha && alpha > -LARGE_NUM {
+                // Fail low
+                beta = (alpha + beta) / 2;
+                alpha = (score - delta).max(-LARGE_NUM);
+            } else if score >= beta && beta < LARGE_NUM {
+                // Fail high
+                beta = (score + delta).min(LARGE_NUM);
+            } else {
+                return score;
+            }
+
+            delta += delta / 2;
+        }
     }
 
     pub fn check(&self, info: &mut SearchInfo) {
+        // The first iteration always finishes so there is a move to play
+        if self.pv_lines.is_empty() {
+            return;
+        }
+
+        // Counted on every node so a node limit stops at exactly the same point every time
+        if self.node_limit.is_some_and(|nodes| self.nodes >= nodes) {
+            info.stopped = true;
+        }
+
         //log::debug!("CHECKING TIME");
-        if let Some(stop_time) = info.stop_time {
-            if !info.infinite && get_time_ms() > stop_time {
-                info.stopped = true;
+        if (self.nodes & 2047) == 0 {
+            if let Some(stop_time) = info.stop_time {
+                if get_time_ms().wrapping_sub(stop_time) > 0 {
+                    info.stopped = true;
+                }
             }
         }
     }
@@ -232,60 +525,265 @@ impl Search {
         position: &mut Position,
         move_gen: &MoveGenerator,
         mut alpha: i32,
-        beta: i32,
+        mut beta: i32,
         depth: i32,
     ) -> i32 {
         // init PV length
         self.pv.pv_length[self.ply as usize] = self.ply as i32;
-        if !self.searching.load(Ordering::Relaxed) {
+        if !self.searching.load(Ordering::Relaxed) || info.stopped {
             return 0;
         }
 
-        if depth == 0 {
+        if self.ply > 0 && (position.is_repetition() || position.draw_by_fifty_moves()) {
+            return self.draw_score();
+        }
+
+        if depth <= 0 {
             return self.quiescence(info, position, move_gen, alpha, beta);
         }
 
-        /*
-        if (self.nodes & 2047) == 0 {
-            self.check(info);
+        // Extensions can take the path past the end of the PV and killer tables
+        if self.ply as i32 >= MAX_PLY - 1 {
+            return self.evaluate(position, move_gen);
+        }
+
+        self.check(info);
+        if info.stopped {
+            return 0;
         }
-        */
 
         self.nodes += 1;
-        let mut best_so_far: Option<Move> = None;
-        let old_alpha = alpha;
-        let mut moves =
-            move_gen.generate_legal_moves(position, position.state.current_turn(), MoveType::All);
+        self.seldepth = self.seldepth.max(self.ply);
 
-        // let mut moves_with_scores: Vec<(Move, i32)> = self.score_moves(position, &moves);
-        self.order_moves(&position, &mut moves);
+        let pv_node = beta - alpha > 1;
+
+        // Mate distance pruning
+        // Even mating right here can not beat a shorter mate already found
+        if self.ply > 0 {
+            alpha = alpha.max(-MATE + self.ply as i32);
+            beta = beta.min(MATE - self.ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        let side = position.state.current_turn();
+        let in_check = move_gen.attacks_to_king(position, side) != EMPTY_BITBOARD;
+        let original_alpha = alpha;
+
+        // The move being tested for singularity must not be cut by its own TT entry
+        let excluded = self.excluded_moves[self.ply as usize];
+        let tt_entry = if excluded == NULL_MOVE {
+            self.tt.get(position.state.zobrist_hash).map(|mut entry| {
+                entry.score = score_from_tt(entry.score, self.ply);
+                entry
+            })
+        } else {
+            None
+        };
+
+        if let Some(entry) = tt_entry {
+            if self.ply > 0
+                && !pv_node
+                && entry.depth >= depth
+                && (entry.flags == HASH_FLAG_EXACT
+                    || (entry.flags == HASH_FLAG_BETA && entry.score >= beta)
+                    || (entry.flags == HASH_FLAG_ALPHA && entry.score <= alpha))
+            {
+                return entry.score;
+            }
+        }
+
+        if self.ply > 0 && !pv_node && !in_check && excluded == NULL_MOVE {
+            let static_eval = self.evaluate(position, move_gen);
+
+            // Reverse futility pruning
+            // The position is so far above beta that a shallow search will not bring it back
+            if self.params.reverse_futility
+                && depth <= self.params.reverse_futility_depth
+                && beta.abs() < MATE_BOUND
+                && static_eval - self.params.reverse_futility_margin * depth >= beta
+            {
+                return static_eval;
+            }
+
+            // Null move pruning
+            // If passing the turn still fails high a real move will too, this does not hold in
+            // zugzwang so endgames with pawns and at most a lone minor piece are excluded
+            if self.params.null_move
+                && depth >= self.params.null_move_min_depth
+                && static_eval >= beta
+                && self.ply >= self.nmp_min_ply
+                && !position.last_move_was_null()
+                && eval::side_phase(position, side) >= NULL_MOVE_MIN_PHASE
+            {
+                let reduction = self.params.null_move_reduction + depth / 6;
+
+                self.capture_squares[self.ply as usize] = None;
+                self.ply += 1;
+                self.make_null_move(position);
+                let score = -self.negamax(
+                    info,
+                    position,
+                    move_gen,
+                    -beta,
+                    -beta + 1,
+                    (depth - 1 - reduction).max(0),
+                );
+                self.unmake_null_move(position);
+                self.ply -= 1;
+
+                if info.stopped {
+                    return 0;
+                }
+
+                if score >= beta {
+                    // Do not return unproven mate scores
+                    let score = if score >= MATE_BOUND { beta } else { score };
+
+                    if depth < self.params.null_move_verify_depth {
+                        return score;
+                    }
+
+                    // Verification search without null moves for the next plies
+                    let verify_depth = (depth - reduction).max(0);
+                    self.nmp_min_ply = self.ply + (3 * verify_depth / 4) as u8;
+                    let verified =
+                        self.negamax(info, position, move_gen, beta - 1, beta, verify_depth);
+                    self.nmp_min_ply = 0;
+
+                    if verified >= beta {
+                        return score;
+                    }
+                }
+            }
+        }
+
+        let mut moves = move_gen.generate_legal_moves(position, side, MoveType::All);
+
+        self.order_moves(position, move_gen, &mut moves);
+
+        // Search the TT move first
+        let tt_move = tt_entry.map_or(NULL_MOVE, |entry| entry.best);
+        Self::move_to_front(&mut moves, tt_move);
+
+        let mut best_score = -LARGE_NUM;
+        let mut best_move = NULL_MOVE;
+        let mut moves_searched = 0;
+        let mut quiets_searched = MoveList::new();
 
         for i in 0..moves.len() {
-            //self.pick_move(&mut moves_with_scores, i as i32);
             let mv = moves.get(i);
 
+            if mv == excluded || (self.ply == 0 && self.skip_root_move(info, mv)) {
+                continue;
+            }
+
+            let quiet = mv.move_type() == MoveType::Quiet || mv.move_type() == MoveType::Castle;
+
+            // Late move pruning
+            // Quiet moves ordered this late at shallow depth are very unlikely to matter
+            if self.params.late_move_pruning
+                && !pv_node
+                && !in_check
+                && quiet
+                && depth <= self.params.lmp_depth
+                && moves_searched >= self.params.lmp_move_count(depth)
+                && best_score > -MATE_BOUND
+            {
+                continue;
+            }
+
+            if self.ply == 0 && self.elapsed() >= CURRMOVE_DELAY_MS {
+                let number = moves_searched as usize + 1;
+                self.notify(|observer| observer.on_root_move(depth, mv, number));
+            }
+
+            let can_extend = self.path_extensions < self.depth as i32;
+            let singular = can_extend
+                && mv == tt_move
+                && self.singular(info, position, move_gen, mv, tt_entry, depth);
+
+            let capture = position.pieces[mv.target() as usize].is_some()
+                || mv.move_type() == MoveType::EnPassant;
+            self.capture_squares[self.ply as usize] = capture.then(|| mv.target());
+
             self.ply += 1;
-            position.make_move(mv);
-            let score = -self.negamax(info, position, move_gen, -beta, -alpha, depth - 1);
+            self.make_move(position, mv);
+
+            let gives_check =
+                move_gen.attacks_to_king(position, position.state.current_turn()) != EMPTY_BITBOARD;
+
+            // Extensions
+            // Forcing moves are searched one ply deeper, at most once per move and no more
+            // often on a path than the iteration depth
+            let extension = if can_extend
+                && (singular
+                    || gives_check
+                    || (pv_node && capture && self.is_recapture(mv))
+                    || Self::is_seventh_rank_push(mv, side))
+            {
+                1
+            } else {
+                0
+            };
+            let new_depth = depth - 1 + extension;
+            self.path_extensions += extension;
+
+            // Principal variation search
+            // The first move is searched with the full window, the rest only have to prove
+            // they are not better than alpha and are re-searched if they are
+            let score = if moves_searched == 0 {
+                -self.negamax(info, position, move_gen, -beta, -alpha, new_depth)
+            } else {
+                let reduction = if self.params.late_move_reductions
+                    && quiet
+                    && !in_check
+                    && !gives_check
+                    && extension == 0
+                    && depth >= self.params.lmr_min_depth
+                    && moves_searched >= self.params.lmr_min_moves
+                {
+                    self.late_move_reduction(mv, side, depth, moves_searched, pv_node)
+                } else {
+                    0
+                };
+
+                // Late move reductions
+                // Quiet moves ordered late are searched shallower first and only get the full
+                // depth if they turn out to beat alpha
+                let mut score = -self.negamax(
+                    info,
+                    position,
+                    move_gen,
+                    -alpha - 1,
+                    -alpha,
+                    new_depth - reduction,
+                );
+
+                if score > alpha && reduction > 0 {
+                    score = -self.negamax(info, position, move_gen, -alpha - 1, -alpha, new_depth);
+                }
+
+                if score > alpha && score < beta {
+                    -self.negamax(info, position, move_gen, -beta, -alpha, new_depth)
+                } else {
+                    score
+                }
+            };
+
+            self.path_extensions -= extension;
             self.ply -= 1;
-            position.unmake();
+            self.unmake(position);
+            moves_searched += 1;
 
             if info.stopped {
                 return 0;
             }
 
-            // Fail-hard beta cutoff
-            if score >= beta {
-                // Store Killer moves
-                if mv.move_type() != MoveType::Capture {
-                    self.killer_moves[1][self.ply as usize] =
-                        self.killer_moves[0][self.ply as usize];
-                    self.killer_moves[0][self.ply as usize] = mv;
-                }
-
-                // Move is too "good" (fails high)
-                // Opponent will avoid this position
-                return beta;
+            if score > best_score {
+                best_score = score;
+                best_move = mv;
             }
 
             // Better move found
@@ -304,11 +802,22 @@ impl Search {
 
                 // Adjust PV length
                 self.pv.pv_length[self.ply as usize] = self.pv.pv_length[(self.ply + 1) as usize];
+            }
 
-                let root_move = self.ply == 0;
-                if root_move {
-                    best_so_far = Some(mv);
+            // Fail-soft beta cutoff
+            // The PV is updated first, a cutoff against a mate distance bound is an exact score
+            if score >= beta {
+                if quiet {
+                    self.update_quiet_heuristics(position, mv, &quiets_searched, depth);
                 }
+
+                // Move is too "good" (fails high)
+                // Opponent will avoid this position
+                break;
+            }
+
+            if quiet {
+                quiets_searched.push(mv);
             }
         }
 
@@ -316,20 +825,157 @@ impl Search {
             if position.checkmate(move_gen) {
                 return -MATE + self.ply as i32;
             } else {
-
-                //return -100;
-                return 0;
+                return self.draw_score();
             }
         }
 
-        if old_alpha != alpha {
-            self.best_move = best_so_far;
+        if !self.searching.load(Ordering::Relaxed) {
+            return 0;
+        }
+
+        // A root restricted by MultiPV or searchmoves does not score the whole position
+        let restricted_root = self.ply == 0
+            && (!self.excluded_root_moves.is_empty() || !info.search_moves().is_empty());
+
+        if moves_searched > 0 && excluded == NULL_MOVE && !restricted_root {
+            let flags = if best_score >= beta {
+                HASH_FLAG_BETA
+            } else if best_score > original_alpha {
+                HASH_FLAG_EXACT
+            } else {
+                HASH_FLAG_ALPHA
+            };
+
+            self.tt.put(
+                position.state.zobrist_hash,
+                Entry {
+                    hash: position.state.zobrist_hash,
+                    depth,
+                    flags,
+                    score: score_to_tt(best_score, self.ply),
+                    best: best_move,
+                },
+            );
+        }
+
+        best_score
+    }
+
+    /**
+     * Score of a draw for the side to move, contempt is from the point of view of the side
+     * to move at the root
+     * */
+    fn draw_score(&self) -> i32 {
+        if self.ply.is_multiple_of(2) {
+            -self.params.contempt
+        } else {
+            self.params.contempt
+        }
+    }
+
+    /**
+     * Singular extension test, the TT move is singular if every other move fails low
+     * against a margin below its TT score
+     * */
+    fn singular(
+        &mut self,
+        info: &mut SearchInfo,
+        position: &mut Position,
+        move_gen: &MoveGenerator,
+        mv: Move,
+        tt_entry: Option<Entry>,
+        depth: i32,
+    ) -> bool {
+        let entry = match tt_entry {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        if self.ply == 0
+            || depth < SINGULAR_DEPTH
+            || entry.flags == HASH_FLAG_ALPHA
+            || entry.depth < depth - SINGULAR_TT_DEPTH_MARGIN
+            || entry.score.abs() >= MATE_BOUND
+        {
+            return false;
+        }
+
+        let singular_beta = entry.score - 2 * depth;
+
+        self.excluded_moves[self.ply as usize] = mv;
+        let score = self.negamax(
+            info,
+            position,
+            move_gen,
+            singular_beta - 1,
+            singular_beta,
+            (depth - 1) / 2,
+        );
+        self.excluded_moves[self.ply as usize] = NULL_MOVE;
+
+        score < singular_beta
+    }
+
+    /**
+     * Called after mv is made, true if it captures back on the square the opponent just
+     * captured on
+     * */
+    fn is_recapture(&self, mv: Move) -> bool {
+        self.ply >= 2 && self.capture_squares[self.ply as usize - 2] == Some(mv.target())
+    }
+
+    /**
+     * A pawn reaching the relative 7th rank is always passed and about to promote
+     * */
+    fn is_seventh_rank_push(mv: Move, side: Side) -> bool {
+        let rank = mv.target() as usize / 8;
+        mv.piece() == Piece::Pawn
+            && ((side == Side::White && rank == 6) || (side == Side::Black && rank == 1))
+    }
+
+    /**
+     * Reduction for a late quiet move, killers and moves with a good history are reduced less
+     * */
+    fn late_move_reduction(
+        &self,
+        mv: Move,
+        side: Side,
+        depth: i32,
+        moves_searched: i32,
+        pv_node: bool,
+    ) -> i32 {
+        // Called after the move is made so the killers are one ply back
+        let ply = self.ply as usize - 1;
+        let mut reduction = self.params.lmr_reduction(depth, moves_searched);
+
+        if pv_node {
+            reduction -= 1;
+        }
+
+        if self.killer_moves[0][ply] == mv || self.killer_moves[1][ply] == mv {
+            reduction -= 1;
         }
 
-        alpha
+        reduction -= self.history_moves[side as usize][mv.from() as usize][mv.target() as usize]
+            / HISTORY_REDUCTION_DIVISOR;
+
+        reduction.clamp(0, (depth - 2).max(0))
     }
 
-    fn quiescence(
+    /**
+     * Root moves outside of `go searchmoves` or already reported by MultiPV are not searched
+     * */
+    fn skip_root_move(&self, info: &SearchInfo, mv: Move) -> bool {
+        let search_moves = info.search_moves();
+        self.excluded_root_moves.contains(&mv)
+            || (!search_moves.is_empty() && !search_moves.contains(&mv))
+    }
+
+    /**
+     * Searches captures and queen promotions until the position is quiet, or every evasion
+     * when in check
+     * */
+    pub(crate) fn quiescence(
         &mut self,
         info: &mut SearchInfo,
         position: &mut Position,
@@ -337,212 +983,407 @@ impl Search {
         mut alpha: i32,
         beta: i32,
     ) -> i32 {
+        // init PV length
+        self.pv.pv_length[self.ply as usize] = self.ply as i32;
+        self.check(info);
+        if info.stopped {
+            return 0;
+        }
+
         self.nodes += 1;
-        let eval = self.evaluate(position);
+        self.seldepth = self.seldepth.max(self.ply);
 
-        // Fail-hard beta cutoff
-        if eval >= beta {
-            // Move is too "good" (fails high)
-            // Opponent will avoid this position
-            return beta;
+        // Evasions can go on past the end of the killer table
+        if self.ply as i32 >= MAX_PLY - 1 {
+            return self.evaluate(position, move_gen);
         }
 
-        // Better Move found is eval > alpha
-        // PV Move
-        alpha = alpha.max(eval);
+        let original_alpha = alpha;
+        let tt_entry = self.tt.get(position.state.zobrist_hash).map(|mut entry| {
+            entry.score = score_from_tt(entry.score, self.ply);
+            entry
+        });
 
-        let mut captures: MoveList = move_gen.generate_legal_moves(
-            position,
-            position.state.current_turn(),
-            MoveType::Capture,
-        );
+        // Every entry is at least as deep as quiescence
+        if let Some(entry) = tt_entry {
+            if entry.flags == HASH_FLAG_EXACT
+                || (entry.flags == HASH_FLAG_BETA && entry.score >= beta)
+                || (entry.flags == HASH_FLAG_ALPHA && entry.score <= alpha)
+            {
+                return entry.score;
+            }
+        }
 
-        // let mut captures_with_scores = self.score_moves(position, &mut captures);
-        self.order_moves(position, &mut captures);
+        let side = position.state.current_turn();
+        let in_check = move_gen.attacks_to_king(position, side) != EMPTY_BITBOARD;
 
-        for i in 0..captures.len() {
-            //self.pick_move(&mut captures_with_scores, i as i32);
-            let capture = captures.get(i);
-            if capture == NULL_MOVE {
-                continue;
+        // Standing pat is not an option when in check
+        let stand_pat = if in_check {
+            None
+        } else {
+            Some(self.evaluate(position, move_gen))
+        };
+
+        if let Some(eval) = stand_pat {
+            // Fail-soft beta cutoff
+            if eval >= beta {
+                return eval;
+            }
+
+            alpha = alpha.max(eval);
+        }
+
+        let mut moves = if in_check {
+            move_gen.generate_legal_moves(position, side, MoveType::All)
+        } else {
+            Self::tactical_moves(position, move_gen, side)
+        };
+
+        self.order_moves(position, move_gen, &mut moves);
+        let tt_move = tt_entry.map_or(NULL_MOVE, |entry| entry.best);
+        Self::move_to_front(&mut moves, tt_move);
+
+        // Mated if no evasion is found
+        let mut best_score = stand_pat.unwrap_or(-MATE + self.ply as i32);
+        let mut best_move = NULL_MOVE;
+
+        for i in 0..moves.len() {
+            let mv = moves.get(i);
+
+            if let Some(eval) = stand_pat {
+                // Delta pruning
+                // Even winning the captured piece with a margin to spare does not reach alpha
+                if eval + Self::material_gain(position, mv) + DELTA_MARGIN <= alpha {
+                    continue;
+                }
+
+                // Captures that lose material in the exchange
+                if !position.see_ge(move_gen, mv, 0) {
+                    continue;
+                }
             }
 
             self.ply += 1;
-            position.make_move(capture);
+            self.make_move(position, mv);
             let eval = -self.quiescence(info, position, move_gen, -beta, -alpha);
-            log::info!("EVAL: {}", eval);
             self.ply -= 1;
-            position.unmake();
+            self.unmake(position);
 
             if info.stopped {
                 return 0;
             }
 
-            // Fail-hard beta cutoff
+            if eval > best_score {
+                best_score = eval;
+                best_move = mv;
+            }
+
+            if eval > alpha {
+                alpha = eval;
+
+                // Store PV Move and copy the captures that follow it
+                self.pv.pv_table[self.ply as usize][self.ply as usize] = Some(mv);
+                for next_ply in (self.ply + 1) as i32..self.pv.pv_length[(self.ply + 1) as usize] {
+                    self.pv.pv_table[self.ply as usize][next_ply as usize] =
+                        self.pv.pv_table[(self.ply + 1) as usize][next_ply as usize];
+                }
+                self.pv.pv_length[self.ply as usize] = self.pv.pv_length[(self.ply + 1) as usize];
+            }
+
+            // Fail-soft beta cutoff
             if eval >= beta {
-                // Move is too "good" (fails high)
-                // Opponent will avoid this position
-                return beta;
+                break;
             }
+        }
 
-            // Better Move found is eval > alpha
-            // PV Move
-            alpha = alpha.max(eval);
+        // Never replaces an entry of the main search, which holds a better move to try first
+        let slot = self.tt.slot(position.state.zobrist_hash);
+        if tt_entry.is_none() && slot.is_none_or(|entry| entry.depth <= 0) {
+            let flags = if best_score >= beta {
+                HASH_FLAG_BETA
+            } else if best_score > original_alpha {
+                HASH_FLAG_EXACT
+            } else {
+                HASH_FLAG_ALPHA
+            };
+
+            self.tt.put(
+                position.state.zobrist_hash,
+                Entry {
+                    hash: position.state.zobrist_hash,
+                    depth: 0,
+                    flags,
+                    score: score_to_tt(best_score, self.ply),
+                    best: best_move,
+                },
+            );
+        }
+
+        best_score
+    }
+
+    /**
+     * Legal captures, en passant and queen promotions, under promotions are left out
+     * */
+    pub fn tactical_moves(
+        position: &mut Position,
+        move_gen: &MoveGenerator,
+        side: Side,
+    ) -> MoveList {
+        let mut moves = move_gen.generate_legal_moves(position, side, MoveType::Capture);
+
+        // Quiet moves are only generated for the promotions among them
+        let seventh_rank = match side {
+            Side::White => SEVENTH_RANK,
+            Side::Black => SECOND_RANK,
+        };
+
+        if position.piece_bitboard(Piece::Pawn, side) & seventh_rank != EMPTY_BITBOARD {
+            let quiets = move_gen.generate_legal_moves(position, side, MoveType::Quiet);
+            for i in 0..quiets.len() {
+                let mv = quiets.get(i);
+                if mv.promotion_piece() == Some(Piece::Queen) {
+                    moves.push(mv);
+                }
+            }
+        }
+
+        for i in (0..moves.len()).rev() {
+            let promotion = moves.get(i).promotion_piece();
+            if promotion.is_some_and(|piece| piece != Piece::Queen) {
+                moves.remove(i);
+            }
         }
-        alpha
+
+        moves
     }
 
-    fn order_moves(&self, position: &Position, moves: &mut MoveList) {
+    /**
+     * Material won by mv, the captured piece plus what a promotion adds
+     * */
+    fn material_gain(position: &Position, mv: Move) -> i32 {
+        let captured = match mv.move_type() {
+            MoveType::EnPassant => SEE_VALUES[Piece::Pawn as usize],
+            _ => position.pieces[mv.target() as usize]
+                .map_or(0, |(_, piece)| SEE_VALUES[piece as usize]),
+        };
+
+        let promotion = mv.promotion_piece().map_or(0, |piece| {
+            SEE_VALUES[piece as usize] - SEE_VALUES[Piece::Pawn as usize]
+        });
+
+        captured + promotion
+    }
+
+    /**
+     * Moves mv to the front of moves, keeping the order of the others
+     * */
+    fn move_to_front(moves: &mut MoveList, mv: Move) {
+        let len = moves.len();
+        if let Some(index) = moves.list[0..len].iter().position(|m| *m == mv) {
+            moves.list[0..=index].rotate_right(1);
+        }
+    }
+
+    fn order_moves(&self, position: &Position, move_gen: &MoveGenerator, moves: &mut MoveList) {
+        let side = position.state.current_turn();
+        let previous = [position.previous_move(1), position.previous_move(2)];
+        let counter_move = previous[0].map_or(NULL_MOVE, |prev| {
+            self.counter_moves[side as usize][prev.piece() as usize][prev.target() as usize]
+        });
+
         let len = moves.len();
-        moves.list[0..len].sort_by(|a, b| {
-            self.score_move(position, b)
-                .cmp(&self.score_move(position, a))
+        moves.list[0..len].sort_by_cached_key(|mv| {
+            Reverse(self.score_move(position, move_gen, *mv, side, counter_move, &previous))
         });
     }
 
-    fn score_move(&self, position: &Position, mv: &Move) -> i32 {
-        if mv.move_type() == MoveType::Capture {
-            let piece_captured = position.pieces[mv.target() as usize].unwrap().1;
-            // MVV_LVA[piece_captured as usize][mv.piece() as usize] + 200
-            //let res = (WEIGHTS[piece_captured as usize] - WEIGHTS[mv.piece() as usize] / 10).abs();
-            //res
-            //MVV_LVA[piece_captured as usize][mv.piece() as usize] as i32
-            MVV_LVA[mv.piece() as usize][piece_captured as usize] as i32
-        } else {
-            /*
-            // score 1st killer move
-            if self.killer_moves[0][self.ply as usize] == *mv {
-                log::info!("FIRST KILLER");
-                9000
+    fn score_move(
+        &self,
+        position: &Position,
+        move_gen: &MoveGenerator,
+        mv: Move,
+        side: Side,
+        counter_move: Move,
+        previous: &[Option<Move>; 2],
+    ) -> i32 {
+        let captured = match mv.move_type() {
+            MoveType::EnPassant => Some(Piece::Pawn),
+            _ => position.pieces[mv.target() as usize].map(|(_, piece)| piece),
+        };
+
+        // Queen promotions are scored like a pawn capturing a queen
+        if let Some(promotion) = mv.promotion_piece() {
+            if promotion != Piece::Queen {
+                return UNDER_PROMOTION_SCORE;
             }
-            // score 2nd killer move
-            else if self.killer_moves[1][self.ply as usize] == *mv {
-                log::info!("SECOND KILLER");
-                8000
+
+            return CAPTURE_SCORE
+                + MVV_LVA[Piece::Queen as usize][Piece::Pawn as usize]
+                + captured.map_or(0, |piece| MVV_LVA[piece as usize][Piece::Pawn as usize]);
+        }
+
+        if let Some(piece_captured) = captured {
+            let mvv_lva = MVV_LVA[piece_captured as usize][mv.piece() as usize];
+
+            // Taking a piece worth as much can not lose material, no exchange to look at
+            if SEE_VALUES[mv.piece() as usize] > SEE_VALUES[piece_captured as usize]
+                && !position.see_ge(move_gen, mv, 0)
+            {
+                return LOSING_CAPTURE_SCORE + mvv_lva;
             }
-            // score history move
-            else {
-                log::info!("HISTORY");
-                self.history_moves[mv.target() as usize][position.state.current_turn() as usize]
-                    [mv.piece() as usize]
+
+            return CAPTURE_SCORE + mvv_lva;
+        }
+
+        // Quiescence can go deeper than the killer table
+        let ply = self.ply as usize;
+        if ply < MAX_PLY as usize {
+            if self.killer_moves[0][ply] == mv {
+                return FIRST_KILLER_SCORE;
+            }
+
+            if self.killer_moves[1][ply] == mv {
+                return SECOND_KILLER_SCORE;
             }
-            */
-            0
         }
+
+        if mv == counter_move {
+            return COUNTER_MOVE_SCORE;
+        }
+
+        let mut score = self.history_moves[side as usize][mv.from() as usize][mv.target() as usize];
+        for prev in previous.iter().flatten() {
+            score += self.continuation_history[side as usize][prev.piece() as usize]
+                [prev.target() as usize][mv.piece() as usize][mv.target() as usize];
+        }
+
+        score
     }
 
-    /*
-    fn score_moves(&self, position: &Position, moves: &MoveList) -> Vec<(Move, i32)> {
-        let mut moves_with_scores = Vec::with_capacity(30);
-        for i in 0..moves.len() {
-            let mv = moves.get(i);
-            let value = if mv.move_type() == MoveType::Capture {
-                let piece_captured = position.pieces[mv.target() as usize].unwrap().1;
-                //return WEIGHTS[piece_captured as usize] - WEIGHTS[mv.piece() as usize] / 10;
-                MVV_LVA[piece_captured as usize][mv.piece() as usize] + 10_000
-            }
-            // Score quiet move
-            else {
-                // score 1st killer move
-                if self.killer_moves[0][self.ply as usize] == mv {
-                    9000
-                }
-                // score 2nd killer move
-                else if self.killer_moves[1][self.ply as usize] == mv {
-                    8000
-                }
-                // score history move
-                else {
-                    self.history_moves[mv.target() as usize][position.state.current_turn() as usize]
-                        [mv.piece() as usize]
-                }
-            };
-            moves_with_scores.push((mv, value));
-        }
-
-        return moves_with_scores;
-    }
-
-    fn pick_move(&self, moves_with_scores: &mut Vec<(Move, i32)>, start_index: i32) {
-        for i in (start_index + 1) as usize..moves_with_scores.len() {
-            if moves_with_scores[i].1 > moves_with_scores[start_index as usize].1 {
-                moves_with_scores.swap(start_index as usize, i);
-            }
-        }
-    }
-    */
-
-    fn evaluate(&self, position: &Position) -> i32 {
-        let mut white_score = 0;
-        let mut black_score = 0;
-
-        for side in Side::iter() {
-            for piece in Piece::iter() {
-                let mut bitboard = position.piece_bitboard(piece, side);
-
-                while bitboard != EMPTY_BITBOARD {
-                    let square = bitboard.bitscan_forward_reset();
-
-                    let index = if side == Side::White {
-                        FLIP[square as usize]
-                    } else {
-                        square as usize
-                    };
-
-                    match piece {
-                        Piece::Pawn => {
-                            if side == Side::White {
-                                white_score += PAWNS_SQ[index];
-                            } else {
-                                black_score += PAWNS_SQ[index];
-                            }
-                        }
-                        Piece::Bishop => {
-                            if side == Side::White {
-                                white_score += BISHOP_SQ[index];
-                            } else {
-                                black_score += BISHOP_SQ[index];
-                            }
-                        }
-                        Piece::Knight => {
-                            if side == Side::White {
-                                white_score += KNIGHT_SQ[index];
-                            } else {
-                                black_score += KNIGHT_SQ[index];
-                            }
-                        }
-                        Piece::Rook => {
-                            if side == Side::White {
-                                white_score += ROOK_SQ[index];
-                            } else {
-                                black_score += ROOK_SQ[index];
-                            }
-                        }
-                        Piece::Queen => {
-                            if side == Side::White {
-                                white_score += QUEEN_SQ[index];
-                            } else {
-                                black_score += QUEEN_SQ[index];
-                            }
-                        }
-                        Piece::King => {
-                            if side == Side::White {
-                                white_score += KING_SQ[index];
-                            } else {
-                                black_score += KING_SQ[index];
-                            }
-                        }
-                    }
-                }
-            }
+    /**
+     * Rewards the quiet move that caused a beta cutoff and penalises the quiet moves
+     * searched before it
+     * */
+    fn update_quiet_heuristics(
+        &mut self,
+        position: &Position,
+        mv: Move,
+        quiets_searched: &MoveList,
+        depth: i32,
+    ) {
+        let ply = self.ply as usize;
+        let side = position.state.current_turn();
+        let previous = [position.previous_move(1), position.previous_move(2)];
+
+        // Store Killer moves
+        if self.killer_moves[0][ply] != mv {
+            self.killer_moves[1][ply] = self.killer_moves[0][ply];
+            self.killer_moves[0][ply] = mv;
         }
 
-        let material_score = white_score - black_score;
+        if let Some(prev) = previous[0] {
+            self.counter_moves[side as usize][prev.piece() as usize][prev.target() as usize] = mv;
+        }
 
-        if position.state.current_turn() == Side::White {
-            material_score
-        } else {
-            -material_score
+        let bonus = (16 * depth * depth).min(MAX_HISTORY_BONUS);
+        self.update_history(side, mv, &previous, bonus);
+        for i in 0..quiets_searched.len() {
+            self.update_history(side, quiets_searched.get(i), &previous, -bonus);
         }
     }
+
+    fn update_history(&mut self, side: Side, mv: Move, previous: &[Option<Move>; 2], bonus: i32) {
+        Self::apply_gravity(
+            &mut self.history_moves[side as usize][mv.from() as usize][mv.target() as usize],
+            bonus,
+        );
+
+        for prev in previous.iter().flatten() {
+            Self::apply_gravity(
+                &mut self.continuation_history[side as usize][prev.piece() as usize]
+                    [prev.target() as usize][mv.piece() as usize][mv.target() as usize],
+                bonus,
+            );
+        }
+    }
+
+    /**
+     * History gravity, the closer an entry is to MAX_HISTORY the less a bonus moves it,
+     * so entries stay bounded and old results fade
+     * */
+    fn apply_gravity(entry: &mut i32, bonus: i32) {
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    fn evaluate(&mut self, position: &Position, move_gen: &MoveGenerator) -> i32 {
+        self.evaluator.evaluate(position, move_gen)
+    }
+
+    fn make_move(&mut self, position: &mut Position, mv: Move) {
+        self.evaluator.on_make_move(position, mv);
+        position.make_move(mv);
+    }
+
+    fn unmake(&mut self, position: &mut Position) {
+        position.unmake();
+        self.evaluator.on_unmake_move(position);
+    }
+
+    fn make_null_move(&mut self, position: &mut Position) {
+        self.evaluator.on_make_null_move(position);
+        position.make_null_move();
+    }
+
+    fn unmake_null_move(&mut self, position: &mut Position) {
+        position.unmake_null_move();
+        self.evaluator.on_unmake_null_move(position);
+    }
+}
+
+/**
+ * Moves until mate for a mate score, negative when the side to move is getting mated
+ * */
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score >= MATE_BOUND {
+        Some((MATE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/**
+ * Formats score as "cp <n>" or "mate <n>" for info lines
+ * */
+pub fn uci_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/**
+ * Mate scores are relative to the root while searching but stored relative to the
+ * position in the TT, so they stay correct when the position is reached at another ply
+ * */
+fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
 }
@@ -0,0 +1,82 @@
+use crate::params::SearchParams;
+use crate::pv::PvLine;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+pub const MAX_SKILL_LEVEL: i32 = 20;
+pub const MIN_ELO: i32 = 800;
+pub const MAX_ELO: i32 = 2400;
+
+// Lines searched to pick a weaker move from
+const SKILL_MULTI_PV: usize = 4;
+
+// Largest score gap between the best and worst line that adds to the random push
+const SKILL_MAX_DELTA: i32 = 100;
+
+// Deterministic searches always pick the same move
+const SKILL_SEED: u64 = 0x5348494E4F4249;
+
+/**
+ * A weakened search, shallower and with a worse move picked among the MultiPV lines
+ * */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    /// 0 is the weakest, below MAX_SKILL_LEVEL
+    pub level: i32,
+}
+
+impl Skill {
+    /**
+     * Returns the skill set by "Skill Level" or "UCI_LimitStrength" with "UCI_Elo",
+     * None at full strength
+     * */
+    pub fn from_params(params: &SearchParams) -> Option<Skill> {
+        let level = if params.limit_strength {
+            (params.elo - MIN_ELO) * MAX_SKILL_LEVEL / (MAX_ELO - MIN_ELO)
+        } else {
+            params.skill_level
+        };
+
+        if level >= MAX_SKILL_LEVEL {
+            return None;
+        }
+
+        Some(Skill {
+            level: level.max(0),
+        })
+    }
+
+    pub fn max_depth(&self) -> i32 {
+        1 + self.level / 2
+    }
+
+    pub fn max_nodes(&self) -> u64 {
+        1000 << (self.level / 2)
+    }
+
+    pub fn multi_pv(&self, multi_pv: usize) -> usize {
+        multi_pv.max(SKILL_MULTI_PV)
+    }
+
+    /**
+     * Picks one of `lines`, sorted by score, weaker levels are more likely to pick a worse one
+     * */
+    pub fn pick<'a>(&self, lines: &'a [PvLine], deterministic: bool) -> Option<&'a PvLine> {
+        let mut rng = if deterministic {
+            ChaChaRng::seed_from_u64(SKILL_SEED)
+        } else {
+            ChaChaRng::from_entropy()
+        };
+
+        let top = lines.first()?.score;
+        let delta = (top - lines.last()?.score).min(SKILL_MAX_DELTA);
+        let weakness = 120 - 2 * self.level;
+
+        lines.iter().max_by_key(|line| {
+            // Makes up for most of the gap to the best line, plus a random amount
+            let push = (weakness * (top - line.score) + delta * rng.gen_range(0..weakness)) / 128;
+            line.score + push
+        })
+    }
+}
@@ -1,39 +1,140 @@
 use crate::mov::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-const HASH_SIZE: usize = 0x400000;
-const HASH_FLAG_EXACT: i32 = 0;
-const HASH_FLAG_ALPHA: i32 = 1;
-const HASH_FLAG_BETA: i32 = 2;
+const HASH_SIZE: usize = 0x80000;
+pub const HASH_FLAG_EXACT: i32 = 0;
+pub const HASH_FLAG_ALPHA: i32 = 1;
+pub const HASH_FLAG_BETA: i32 = 2;
 
-#[derive(Clone, Copy)]
+// Layout of the packed entry data
+const MOVE_MASK: u64 = 0xFFFF_FFFF;
+const SCORE_SHIFT: u32 = 32;
+const DEPTH_SHIFT: u32 = 48;
+const FLAGS_SHIFT: u32 = 56;
+const OCCUPIED: u64 = 1 << 63;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Entry {
-    hash: u64,
-    depth: i32,
-    flags: i32,
-    score: i32,
-    best: Move,
+    pub hash: u64,
+    pub depth: i32,
+    pub flags: i32,
+    pub score: i32,
+    pub best: Move,
+}
+
+impl Entry {
+    fn pack(&self) -> u64 {
+        (self.best.0 as u64 & MOVE_MASK)
+            | ((self.score as i16 as u16 as u64) << SCORE_SHIFT)
+            | ((self.depth.clamp(i8::MIN as i32, i8::MAX as i32) as i8 as u8 as u64) << DEPTH_SHIFT)
+            | ((self.flags as u64 & 0x3) << FLAGS_SHIFT)
+            | OCCUPIED
+    }
+
+    fn unpack(hash: u64, data: u64) -> Entry {
+        Entry {
+            hash,
+            depth: (data >> DEPTH_SHIFT) as u8 as i8 as i32,
+            flags: ((data >> FLAGS_SHIFT) & 0x3) as i32,
+            score: (data >> SCORE_SHIFT) as u16 as i16 as i32,
+            best: Move((data & MOVE_MASK) as u32),
+        }
+    }
 }
+
+/**
+ * Transposition table shared by every clone of the search
+ *
+ * Each slot keeps the packed entry and its hash xor'd with it, so a slot torn by
+ * two threads writing at once is never mistaken for a hit
+ * */
 pub struct TT {
-    table: [Option<Entry>; HASH_SIZE],
+    table: Vec<(AtomicU64, AtomicU64)>,
 }
 
 impl TT {
-    pub fn put(&mut self, hash: u64, entry: Entry) {}
+    pub fn new() -> TT {
+        TT::with_size(HASH_SIZE)
+    }
+
+    /**
+     * Table of `entries` slots, for a search too small to need the usual size
+     * */
+    pub fn with_size(entries: usize) -> TT {
+        TT {
+            table: (0..entries)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+        }
+    }
+
+    /**
+     * Stores entry, an entry for another position is always replaced while an entry for the
+     * same position is only replaced by a deeper or exact search
+     * */
+    pub fn put(&self, hash: u64, entry: Entry) {
+        let index = hash as usize % self.table.len();
+        let (key, data) = &self.table[index];
+
+        if let Some(old) = self.get(hash) {
+            if entry.depth < old.depth && entry.flags != HASH_FLAG_EXACT {
+                return;
+            }
+        }
+
+        let packed = entry.pack();
+        key.store(hash ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
 
     pub fn get(&self, hash: u64) -> Option<Entry> {
         let index = hash as usize % self.table.len();
-        let entry = self.table[index];
+        let (key, data) = &self.table[index];
+        let data = data.load(Ordering::Relaxed);
 
-        if entry.is_some() && entry.unwrap().hash == hash {
-            return entry;
+        if data & OCCUPIED != 0 && key.load(Ordering::Relaxed) ^ data == hash {
+            return Some(Entry::unpack(hash, data));
         }
 
         None
     }
 
-    pub fn clear(&mut self) {
-        for i in 0..self.table.len() {
-            self.table[i] = None;
+    /**
+     * Entry in the slot of hash, whichever position it was stored for
+     * */
+    pub fn slot(&self, hash: u64) -> Option<Entry> {
+        let index = hash as usize % self.table.len();
+        let (key, data) = &self.table[index];
+        let data = data.load(Ordering::Relaxed);
+
+        if data & OCCUPIED != 0 {
+            return Some(Entry::unpack(key.load(Ordering::Relaxed) ^ data, data));
         }
+
+        None
+    }
+
+    /**
+     * Permille of the table in use, estimated from the first thousand slots
+     * */
+    pub fn hashfull(&self) -> usize {
+        self.table
+            .iter()
+            .take(1000)
+            .filter(|(_, data)| data.load(Ordering::Relaxed) & OCCUPIED != 0)
+            .count()
+    }
+
+    pub fn clear(&self) {
+        for (key, data) in self.table.iter() {
+            key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for TT {
+    fn default() -> Self {
+        Self::new()
     }
 }
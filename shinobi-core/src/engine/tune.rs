@@ -0,0 +1,586 @@
+use crate::eval::{self, Param, Score, Tracer, EG_TABLES, MAX_PHASE, MG_TABLES, PIECE_VALUES};
+use crate::pawns;
+use crate::pieces;
+use crate::search::{Search, LARGE_NUM};
+use crate::tt::TT;
+use crate::MoveGenerator;
+use crate::Piece;
+use crate::Position;
+use crate::SearchInfo;
+use crate::Side;
+
+use std::fs;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
+
+const QUIET_HASH_SIZE: usize = 0x1000;
+
+pub const LEARNING_RATE: f64 = 1.0;
+
+// Adam decay rates of the gradient and of its square
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+// First index of each group of parameters
+const PIECE_SQUARES: usize = 6;
+const PAWN_TERMS: usize = PIECE_SQUARES + 6 * 64;
+const PIECE_TERMS: usize = PAWN_TERMS + 22;
+pub const PARAM_COUNT: usize = PIECE_TERMS + 20;
+
+const TABLE_NAMES: [&str; 6] = ["PAWNS", "KNIGHT", "BISHOP", "ROOK", "QUEEN", "KING"];
+
+/**
+ * Every tuned parameter in index order
+ * */
+pub fn parameters() -> Vec<Param> {
+    let mut params = Vec::with_capacity(PARAM_COUNT);
+
+    params.extend(Piece::iter().map(Param::PieceValue));
+    for piece in Piece::iter() {
+        params.extend((0..64).map(|index| Param::PieceSquare(piece, index)));
+    }
+
+    params.extend((0..8).map(Param::PassedPawn));
+    params.extend((0..8).map(Param::ConnectedPawn));
+    params.extend([
+        Param::IsolatedPawn,
+        Param::DoubledPawn,
+        Param::BackwardPawn,
+        Param::PassedTheirKingDistance,
+        Param::PassedOurKingDistance,
+        Param::UnstoppablePassedPawn,
+    ]);
+
+    params.extend(Piece::iter().map(Param::Mobility));
+    params.extend(Piece::iter().map(Param::KingAttack));
+    params.extend([
+        Param::PawnShield,
+        Param::KingSemiOpenFile,
+        Param::KingOpenFile,
+        Param::RookOpenFile,
+        Param::RookSemiOpenFile,
+        Param::RookSeventhRank,
+        Param::KnightOutpost,
+        Param::BishopPair,
+    ]);
+
+    params
+}
+
+/**
+ * Position of param in parameters()
+ * */
+pub fn index(param: Param) -> usize {
+    match param {
+        Param::PieceValue(piece) => piece as usize,
+        Param::PieceSquare(piece, index) => PIECE_SQUARES + piece as usize * 64 + index,
+        Param::PassedPawn(rank) => PAWN_TERMS + rank,
+        Param::ConnectedPawn(rank) => PAWN_TERMS + 8 + rank,
+        Param::IsolatedPawn => PAWN_TERMS + 16,
+        Param::DoubledPawn => PAWN_TERMS + 17,
+        Param::BackwardPawn => PAWN_TERMS + 18,
+        Param::PassedTheirKingDistance => PAWN_TERMS + 19,
+        Param::PassedOurKingDistance => PAWN_TERMS + 20,
+        Param::UnstoppablePassedPawn => PAWN_TERMS + 21,
+        Param::Mobility(piece) => PIECE_TERMS + piece as usize,
+        Param::KingAttack(piece) => PIECE_TERMS + 6 + piece as usize,
+        Param::PawnShield => PIECE_TERMS + 12,
+        Param::KingSemiOpenFile => PIECE_TERMS + 13,
+        Param::KingOpenFile => PIECE_TERMS + 14,
+        Param::RookOpenFile => PIECE_TERMS + 15,
+        Param::RookSemiOpenFile => PIECE_TERMS + 16,
+        Param::RookSeventhRank => PIECE_TERMS + 17,
+        Param::KnightOutpost => PIECE_TERMS + 18,
+        Param::BishopPair => PIECE_TERMS + 19,
+    }
+}
+
+/**
+ * Value of param in the evaluation as it is compiled
+ * */
+pub fn current_value(param: Param) -> Score {
+    match param {
+        Param::PieceValue(piece) => PIECE_VALUES[piece as usize],
+        Param::PieceSquare(piece, index) => Score::new(
+            MG_TABLES[piece as usize][index],
+            EG_TABLES[piece as usize][index],
+        ),
+        Param::PassedPawn(rank) => pawns::PASSED_PAWN[rank],
+        Param::ConnectedPawn(rank) => pawns::CONNECTED_PAWN[rank],
+        Param::IsolatedPawn => pawns::ISOLATED_PAWN,
+        Param::DoubledPawn => pawns::DOUBLED_PAWN,
+        Param::BackwardPawn => pawns::BACKWARD_PAWN,
+        Param::PassedTheirKingDistance => Score::new(0, pawns::PASSED_THEIR_KING_DISTANCE),
+        Param::PassedOurKingDistance => Score::new(0, pawns::PASSED_OUR_KING_DISTANCE),
+        Param::UnstoppablePassedPawn => pawns::UNSTOPPABLE_PASSED_PAWN,
+        Param::Mobility(piece) => pieces::MOBILITY[piece as usize],
+        Param::KingAttack(piece) => Score::new(pieces::KING_ATTACK_WEIGHTS[piece as usize], 0),
+        Param::PawnShield => pieces::PAWN_SHIELD,
+        Param::KingSemiOpenFile => pieces::KING_SEMI_OPEN_FILE,
+        Param::KingOpenFile => pieces::KING_OPEN_FILE,
+        Param::RookOpenFile => pieces::ROOK_OPEN_FILE,
+        Param::RookSemiOpenFile => pieces::ROOK_SEMI_OPEN_FILE,
+        Param::RookSeventhRank => pieces::ROOK_SEVENTH_RANK,
+        Param::KnightOutpost => pieces::KNIGHT_OUTPOST,
+        Param::BishopPair => pieces::BISHOP_PAIR,
+    }
+}
+
+/**
+ * Whether the middlegame and endgame halves of param are used, a plain number in the
+ * evaluation only has one of them
+ * */
+fn tuned_halves(param: Param) -> [bool; 2] {
+    match param {
+        Param::KingAttack(_) => [true, false],
+        Param::PassedTheirKingDistance | Param::PassedOurKingDistance => [false, true],
+        _ => [true, true],
+    }
+}
+
+/**
+ * Parses a dataset line, a FEN followed by the game result from White's side as
+ * "1-0", "0-1", "1/2-1/2" or a number from 0 to 1 such as [0.5]
+ *
+ * Only the first four FEN fields are used, move counters may be left out
+ * */
+pub fn parse_entry(line: &str) -> Result<(Position, f64), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 5 {
+        return Err(format!("Missing FEN or result: {}", line));
+    }
+
+    let result = fields[4..]
+        .iter()
+        .find_map(|field| parse_result(field))
+        .ok_or_else(|| format!("Missing result: {}", line))?;
+    let position = Position::from_fen(&fields[..4].join(" "))?;
+
+    Ok((position, result))
+}
+
+fn parse_result(field: &str) -> Option<f64> {
+    let field = field.trim_matches(|c| matches!(c, '[' | ']' | '"' | ';' | ','));
+    match field {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        // Move counters are whole numbers, results written as numbers have a decimal point
+        _ if field.contains('.') => field
+            .parse::<f64>()
+            .ok()
+            .filter(|result| (0.0..=1.0).contains(result)),
+        _ => None,
+    }
+}
+
+/**
+ * Plays out the line the search's quiescence prefers in position, leaving the quiet position
+ * whose static evaluation it returned
+ *
+ * A line cut short by a hash hit is searched again from where it stopped, until the search
+ * no longer wants to play anything
+ * */
+pub fn quiet_position(
+    mut position: Position,
+    move_gen: &MoveGenerator,
+    search: &mut Search,
+) -> Position {
+    loop {
+        search.tt.clear();
+        search.ply = 0;
+        search.quiescence(
+            &mut SearchInfo::new(),
+            &mut position,
+            move_gen,
+            -LARGE_NUM,
+            LARGE_NUM,
+        );
+
+        let line = search.pv.root_line();
+        if line.is_empty() {
+            return position;
+        }
+
+        for mv in line {
+            position.make_move(mv);
+        }
+    }
+}
+
+/**
+ * Search used to find the quiet positions, its small hash table is cleared for every position
+ * */
+pub fn quiet_search() -> Search {
+    let mut search = Search::new();
+    search.tt = Arc::new(TT::with_size(QUIET_HASH_SIZE));
+    search
+}
+
+/**
+ * How much each parameter counts in a position, White minus Black
+ * */
+struct Coefficients(Vec<f64>);
+
+impl Tracer for Coefficients {
+    fn add(&mut self, param: Param, side: Side, count: f64) {
+        match side {
+            Side::White => self.0[index(param)] += count,
+            Side::Black => self.0[index(param)] -= count,
+        }
+    }
+}
+
+/**
+ * A quiet position of the dataset reduced to what the linear evaluation needs
+ * */
+#[derive(Debug, Clone)]
+struct TuneEntry {
+    result: f64,
+
+    /// Share of the middlegame half, the game phase over MAX_PHASE
+    mg_weight: f64,
+
+    /// Non zero coefficients by parameter index
+    coefficients: Vec<(u16, f32)>,
+}
+
+/**
+ * Texel tuning, fits every evaluation parameter to game results with gradient descent
+ *
+ * The evaluation is a sum of parameters times their coefficients tapered by the phase, so
+ * each position is traced once and the tuner works on the coefficients alone. The error is
+ * the mean squared difference between the results and the sigmoid of the evaluation
+ * */
+#[derive(Clone)]
+pub struct Tuner {
+    /// Scaling of the sigmoid, fitted to the starting parameters by fit_k
+    pub k: f64,
+
+    /// Middlegame and endgame value of every parameter, in parameters() order
+    params: Vec<[f64; 2]>,
+    entries: Vec<TuneEntry>,
+    search: Search,
+
+    /// Adam moving averages of the gradient and of its square
+    momentum: Vec<[f64; 2]>,
+    velocity: Vec<[f64; 2]>,
+    epochs: i32,
+}
+
+impl Tuner {
+    pub fn new() -> Tuner {
+        let params: Vec<[f64; 2]> = parameters()
+            .into_iter()
+            .enumerate()
+            .map(|(i, param)| {
+                debug_assert_eq!(index(param), i);
+                let value = current_value(param);
+                [value.mg as f64, value.eg as f64]
+            })
+            .collect();
+
+        Tuner {
+            k: 1.0,
+            params,
+            entries: Vec::new(),
+            search: quiet_search(),
+            momentum: vec![[0.0; 2]; PARAM_COUNT],
+            velocity: vec![[0.0; 2]; PARAM_COUNT],
+            epochs: 0,
+        }
+    }
+
+    /**
+     * Reads a dataset with one position per line, see parse_entry, and returns the number of
+     * positions added
+     * */
+    pub fn load(&mut self, path: &str, move_gen: &MoveGenerator) -> Result<usize, String> {
+        let data = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let before = self.entries.len();
+
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            match parse_entry(line) {
+                Ok((position, result)) => self.add_position(position, result, move_gen),
+                Err(err) => log::error!("{}", err),
+            }
+        }
+
+        Ok(self.entries.len() - before)
+    }
+
+    /**
+     * Adds the quiet position reached from position by quiescence, result is the game result
+     * from White's side
+     * */
+    pub fn add_position(&mut self, position: Position, result: f64, move_gen: &MoveGenerator) {
+        let position = quiet_position(position, move_gen, &mut self.search);
+
+        let mut coefficients = Coefficients(vec![0.0; PARAM_COUNT]);
+        eval::evaluate_params(&position, move_gen, &mut coefficients);
+
+        self.entries.push(TuneEntry {
+            result,
+            mg_weight: position.phase.min(MAX_PHASE) as f64 / MAX_PHASE as f64,
+            coefficients: coefficients
+                .0
+                .iter()
+                .enumerate()
+                .filter(|(_, coefficient)| **coefficient != 0.0)
+                .map(|(i, coefficient)| (i as u16, *coefficient as f32))
+                .collect(),
+        });
+    }
+
+    pub fn positions(&self) -> usize {
+        self.entries.len()
+    }
+
+    /**
+     * Evaluation of position with the current parameters, from White's side
+     * */
+    pub fn linear_eval(&self, position: &Position, move_gen: &MoveGenerator) -> f64 {
+        let mut coefficients = Coefficients(vec![0.0; PARAM_COUNT]);
+        eval::evaluate_params(position, move_gen, &mut coefficients);
+
+        let mg_weight = position.phase.min(MAX_PHASE) as f64 / MAX_PHASE as f64;
+        let (mg, eg) = coefficients.0.iter().zip(self.params.iter()).fold(
+            (0.0, 0.0),
+            |(mg, eg), (coefficient, param)| {
+                (mg + coefficient * param[0], eg + coefficient * param[1])
+            },
+        );
+
+        mg * mg_weight + eg * (1.0 - mg_weight)
+    }
+
+    fn entry_eval(&self, entry: &TuneEntry) -> f64 {
+        let (mg, eg) = entry
+            .coefficients
+            .iter()
+            .fold((0.0, 0.0), |(mg, eg), &(i, coefficient)| {
+                let param = self.params[i as usize];
+                let coefficient = coefficient as f64;
+                (mg + coefficient * param[0], eg + coefficient * param[1])
+            });
+
+        mg * entry.mg_weight + eg * (1.0 - entry.mg_weight)
+    }
+
+    fn sigmoid(k: f64, eval: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+    }
+
+    fn error_with_k(&self, k: f64) -> f64 {
+        let total: f64 = self
+            .entries
+            .iter()
+            .map(|entry| (entry.result - Self::sigmoid(k, self.entry_eval(entry))).powi(2))
+            .sum();
+
+        total / self.entries.len().max(1) as f64
+    }
+
+    /**
+     * Mean squared error of the current parameters over the dataset
+     * */
+    pub fn error(&self) -> f64 {
+        self.error_with_k(self.k)
+    }
+
+    /**
+     * Chooses the sigmoid scaling that best fits the current parameters to the results, ten
+     * times finer each round
+     * */
+    pub fn fit_k(&mut self) -> f64 {
+        let mut step = 0.1;
+
+        for _ in 0..4 {
+            let mut best = (self.error_with_k(self.k), self.k);
+            for i in -10..=10 {
+                let k = self.k + i as f64 * step;
+                if k > 0.0 {
+                    let error = self.error_with_k(k);
+                    if error < best.0 {
+                        best = (error, k);
+                    }
+                }
+            }
+
+            self.k = best.1;
+            step /= 10.0;
+        }
+
+        self.k
+    }
+
+    /**
+     * One step of gradient descent over the whole dataset with Adam step sizes, returns the
+     * error before the step
+     * */
+    pub fn epoch(&mut self, learning_rate: f64) -> f64 {
+        let mut gradient = vec![[0.0; 2]; PARAM_COUNT];
+        let mut error = 0.0;
+
+        for entry in self.entries.iter() {
+            let sigmoid = Self::sigmoid(self.k, self.entry_eval(entry));
+            error += (entry.result - sigmoid).powi(2);
+
+            // Derivative of the squared error by the evaluation, less the constant factors
+            let slope = (sigmoid - entry.result) * sigmoid * (1.0 - sigmoid);
+            let mg_slope = slope * entry.mg_weight;
+            let eg_slope = slope * (1.0 - entry.mg_weight);
+
+            for &(i, coefficient) in entry.coefficients.iter() {
+                gradient[i as usize][0] += mg_slope * coefficient as f64;
+                gradient[i as usize][1] += eg_slope * coefficient as f64;
+            }
+        }
+
+        let n = self.entries.len().max(1) as f64;
+        let scale = 2.0 * self.k * 10f64.ln() / 400.0 / n;
+        self.epochs += 1;
+
+        for (i, param) in parameters().into_iter().enumerate() {
+            for (half, tuned) in tuned_halves(param).into_iter().enumerate() {
+                if !tuned {
+                    continue;
+                }
+
+                let gradient = gradient[i][half] * scale;
+                let momentum = &mut self.momentum[i][half];
+                let velocity = &mut self.velocity[i][half];
+                *momentum = BETA1 * *momentum + (1.0 - BETA1) * gradient;
+                *velocity = BETA2 * *velocity + (1.0 - BETA2) * gradient * gradient;
+
+                let momentum = *momentum / (1.0 - BETA1.powi(self.epochs));
+                let velocity = *velocity / (1.0 - BETA2.powi(self.epochs));
+                self.params[i][half] -= learning_rate * momentum / (velocity.sqrt() + EPSILON);
+            }
+        }
+
+        error / n
+    }
+
+    /**
+     * Current value of param rounded to whole centipawns
+     * */
+    pub fn value(&self, param: Param) -> Score {
+        let [mg, eg] = self.params[index(param)];
+        Score::new(mg.round() as i32, eg.round() as i32)
+    }
+
+    /**
+     * Rust source of every tuned constant, grouped by the file it replaces
+     * */
+    pub fn to_rust(&self) -> String {
+        let score = |param: Param| {
+            let score = self.value(param);
+            format!("Score::new({}, {})", score.mg, score.eg)
+        };
+        let scores = |name: &str, params: Vec<Param>| {
+            let rows: Vec<String> = params
+                .into_iter()
+                .map(|param| format!("    {},\n", score(param)))
+                .collect();
+            format!(
+                "pub const {}: [Score; {}] = [\n{}];\n",
+                name,
+                rows.len(),
+                rows.concat()
+            )
+        };
+
+        let mut out = String::from("// src/engine/eval.rs\n\n");
+        out.push_str(&scores(
+            "PIECE_VALUES",
+            Piece::iter().map(Param::PieceValue).collect(),
+        ));
+
+        for (half, prefix) in ["MG", "EG"].into_iter().enumerate() {
+            for piece in Piece::iter() {
+                let name = TABLE_NAMES[piece as usize];
+                out.push_str(&format!(
+                    "\n#[rustfmt::skip]\nconst {}_{}_SQ: [i32; 64] = [\n",
+                    prefix, name
+                ));
+
+                for rank in 0..8 {
+                    let row: Vec<String> = (0..8)
+                        .map(|file| {
+                            let value = self.value(Param::PieceSquare(piece, rank * 8 + file));
+                            let value = if half == 0 { value.mg } else { value.eg };
+                            format!("{:>3},", value)
+                        })
+                        .collect();
+                    out.push_str(&format!("    {}\n", row.join(" ")));
+                }
+                out.push_str("];\n");
+            }
+        }
+
+        out.push_str("\n// src/engine/pawns.rs\n\n");
+        out.push_str(&scores(
+            "PASSED_PAWN",
+            (0..8).map(Param::PassedPawn).collect(),
+        ));
+        out.push('\n');
+        out.push_str(&scores(
+            "CONNECTED_PAWN",
+            (0..8).map(Param::ConnectedPawn).collect(),
+        ));
+        out.push('\n');
+        for (name, param) in [
+            ("ISOLATED_PAWN", Param::IsolatedPawn),
+            ("DOUBLED_PAWN", Param::DoubledPawn),
+            ("BACKWARD_PAWN", Param::BackwardPawn),
+            ("UNSTOPPABLE_PASSED_PAWN", Param::UnstoppablePassedPawn),
+        ] {
+            out.push_str(&format!("pub const {}: Score = {};\n", name, score(param)));
+        }
+        for (name, param) in [
+            ("PASSED_THEIR_KING_DISTANCE", Param::PassedTheirKingDistance),
+            ("PASSED_OUR_KING_DISTANCE", Param::PassedOurKingDistance),
+        ] {
+            out.push_str(&format!(
+                "pub const {}: i32 = {};\n",
+                name,
+                self.value(param).eg
+            ));
+        }
+
+        out.push_str("\n// src/engine/pieces.rs\n\n");
+        out.push_str(&scores(
+            "MOBILITY",
+            Piece::iter().map(Param::Mobility).collect(),
+        ));
+        let weights: Vec<String> = Piece::iter()
+            .map(|piece| self.value(Param::KingAttack(piece)).mg.to_string())
+            .collect();
+        out.push_str(&format!(
+            "pub const KING_ATTACK_WEIGHTS: [i32; 6] = [{}];\n",
+            weights.join(", ")
+        ));
+        for (name, param) in [
+            ("PAWN_SHIELD", Param::PawnShield),
+            ("KING_SEMI_OPEN_FILE", Param::KingSemiOpenFile),
+            ("KING_OPEN_FILE", Param::KingOpenFile),
+            ("ROOK_OPEN_FILE", Param::RookOpenFile),
+            ("ROOK_SEMI_OPEN_FILE", Param::RookSemiOpenFile),
+            ("ROOK_SEVENTH_RANK", Param::RookSeventhRank),
+            ("KNIGHT_OUTPOST", Param::KnightOutpost),
+            ("BISHOP_PAIR", Param::BishopPair),
+        ] {
+            out.push_str(&format!("pub const {}: Score = {};\n", name, score(param)));
+        }
+
+        out
+    }
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -103,6 +103,21 @@ impl Zobrist {
 
         key
     }
+
+    /**
+     * Hash of the pawns of both sides, made of the same numbers as their part of the full hash
+     * */
+    pub fn generate_pawn_hash(&self, position: &Position) -> u64 {
+        let mut key = 0;
+
+        for side in Side::iter() {
+            for square in position.piece_bitboard(Piece::Pawn, side) {
+                key ^= self.rand_piece_num(side, Piece::Pawn, square);
+            }
+        }
+
+        key
+    }
 }
 
 impl Default for Zobrist {
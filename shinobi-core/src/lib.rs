@@ -15,6 +15,5 @@ pub use strum::IntoEnumIterator;
 pub use strum_macros::EnumIter;
 pub use util::*;
 pub use engine::*;
-pub use bot::*;
 pub use zobrist::*;
 pub use tt::*;
@@ -0,0 +1,148 @@
+use crate::{BitBoard, MoveGenerator, Piece, Position, Side, Square, EMPTY_BITBOARD};
+
+/**
+ * Attack queries on a position, built on the leaper and magic tables
+ * */
+impl MoveGenerator {
+    /**
+     * Pieces of both sides attacking square, sliding pieces are blocked by occupancy
+     *
+     * Pieces missing from occupancy, like those already traded off in an exchange, are left out
+     * */
+    pub fn attackers_to(
+        &self,
+        position: &Position,
+        square: Square,
+        occupancy: BitBoard,
+    ) -> BitBoard {
+        let both = |piece: Piece| {
+            position.piece_bitboard(piece, Side::White)
+                | position.piece_bitboard(piece, Side::Black)
+        };
+
+        let diagonal = both(Piece::Bishop) | both(Piece::Queen);
+        let straight = both(Piece::Rook) | both(Piece::Queen);
+        let sq = square as usize;
+
+        let attackers = (self.pawn_attacks[Side::White as usize][sq]
+            & position.piece_bitboard(Piece::Pawn, Side::Black))
+            | (self.pawn_attacks[Side::Black as usize][sq]
+                & position.piece_bitboard(Piece::Pawn, Side::White))
+            | (self.knight_moves[sq] & both(Piece::Knight))
+            | (self.king_moves[sq] & both(Piece::King))
+            | (self.get_bishop_moves(sq as u64, occupancy) & diagonal)
+            | (self.get_rook_moves(sq as u64, occupancy) & straight);
+
+        attackers & occupancy
+    }
+
+    pub fn is_square_attacked(&self, position: &Position, square: Square, by: Side) -> bool {
+        let attackers = self.attackers_to(position, square, position.main_bitboard);
+        attackers & position.side_bitboards[by as usize] != EMPTY_BITBOARD
+    }
+
+    /**
+     * Pieces giving check to the side to move
+     * */
+    pub fn checkers(&self, position: &Position) -> BitBoard {
+        let side = position.state.current_turn();
+        self.attacks_to_king(position, side)
+    }
+
+    /**
+     * Pieces of side that can not leave the line between their king and an enemy slider
+     * */
+    pub fn pinned_pieces(&self, position: &Position, side: Side) -> BitBoard {
+        let opponent = match side {
+            Side::White => Side::Black,
+            Side::Black => Side::White,
+        };
+        let king = position.king(side);
+        let king_bb = BitBoard(1 << king as u64);
+        let opponent_pieces = position.side_bitboards[opponent as usize];
+        let queens = position.piece_bitboard(Piece::Queen, opponent);
+
+        // Enemy sliders that would attack the king if only enemy pieces were on the board
+        let diagonal_snipers = self.get_bishop_moves(king as u64, opponent_pieces)
+            & (position.piece_bitboard(Piece::Bishop, opponent) | queens);
+        let straight_snipers = self.get_rook_moves(king as u64, opponent_pieces)
+            & (position.piece_bitboard(Piece::Rook, opponent) | queens);
+
+        let mut pinned = EMPTY_BITBOARD;
+        for sniper in diagonal_snipers {
+            let sniper_bb = BitBoard(1 << sniper as u64);
+            let between = self.get_bishop_moves(king as u64, sniper_bb)
+                & self.get_bishop_moves(sniper as u64, king_bb);
+            pinned |= Self::single_blocker(position, between, side);
+        }
+
+        for sniper in straight_snipers {
+            let sniper_bb = BitBoard(1 << sniper as u64);
+            let between = self.get_rook_moves(king as u64, sniper_bb)
+                & self.get_rook_moves(sniper as u64, king_bb);
+            pinned |= Self::single_blocker(position, between, side);
+        }
+
+        pinned
+    }
+
+    /**
+     * Every square side attacks, squares of its own pieces included
+     * */
+    pub fn attacked_squares(&self, position: &Position, side: Side) -> BitBoard {
+        let occupancy = position.main_bitboard;
+        let mut attacks = EMPTY_BITBOARD;
+
+        for from in position.piece_bitboard(Piece::Pawn, side) {
+            attacks |= self.pawn_attacks[side as usize][from as usize];
+        }
+
+        for from in position.piece_bitboard(Piece::Knight, side) {
+            attacks |= self.knight_moves[from as usize];
+        }
+
+        for from in position.piece_bitboard(Piece::Bishop, side) {
+            attacks |= self.get_bishop_moves(from as u64, occupancy);
+        }
+
+        for from in position.piece_bitboard(Piece::Rook, side) {
+            attacks |= self.get_rook_moves(from as u64, occupancy);
+        }
+
+        for from in position.piece_bitboard(Piece::Queen, side) {
+            attacks |= self.get_queen_moves(from as u64, occupancy);
+        }
+
+        for from in position.piece_bitboard(Piece::King, side) {
+            attacks |= self.king_moves[from as usize];
+        }
+
+        attacks
+    }
+
+    /**
+     * Pieces of side, other than the king, attacked by the opponent and not defended
+     * */
+    pub fn hanging_pieces(&self, position: &Position, side: Side) -> BitBoard {
+        let opponent = match side {
+            Side::White => Side::Black,
+            Side::Black => Side::White,
+        };
+        let pieces =
+            position.side_bitboards[side as usize] & !position.piece_bitboard(Piece::King, side);
+
+        pieces & self.attacked_squares(position, opponent) & !self.attacked_squares(position, side)
+    }
+
+    /**
+     * The piece of side in between, if it is the only piece there
+     * */
+    fn single_blocker(position: &Position, between: BitBoard, side: Side) -> BitBoard {
+        let blockers = between & position.main_bitboard;
+        if blockers.0.count_ones() == 1 {
+            blockers & position.side_bitboards[side as usize]
+        } else {
+            EMPTY_BITBOARD
+        }
+    }
+}
@@ -6,14 +6,15 @@ use crate::{
 };
 use strum::IntoEnumIterator;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct MoveGenerator {
     pub knight_moves: [BitBoard; NUM_SQUARES],
     pub pawn_pushes: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     pub pawn_attacks: [[BitBoard; NUM_SQUARES]; NUM_SIDES],
     pub king_moves: [BitBoard; NUM_SQUARES],
-    pub rook_moves: [BitBoard; 102400],
-    pub bishop_moves: [BitBoard; 5248],
+    /// Magic attack tables, on the heap as they are too large for a thread's stack
+    pub rook_moves: Vec<BitBoard>,
+    pub bishop_moves: Vec<BitBoard>,
     pub bishop_tbl: [SMagic; NUM_SQUARES],
     pub rook_tbl: [SMagic; NUM_SQUARES],
 }
@@ -21,8 +22,8 @@ pub struct MoveGenerator {
 impl MoveGenerator {
     pub fn new() -> MoveGenerator {
         let mut move_gen = MoveGenerator {
-            rook_moves: [EMPTY_BITBOARD; 102400],
-            bishop_moves: [EMPTY_BITBOARD; 5248],
+            rook_moves: vec![EMPTY_BITBOARD; 102400],
+            bishop_moves: vec![EMPTY_BITBOARD; 5248],
             knight_moves: [EMPTY_BITBOARD; NUM_SQUARES],
             pawn_pushes: [[EMPTY_BITBOARD; NUM_SQUARES]; NUM_SIDES],
             pawn_attacks: [[EMPTY_BITBOARD; NUM_SQUARES]; NUM_SIDES],
@@ -378,16 +379,16 @@ impl MoveGenerator {
         }
     }
 
-    fn get_queen_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
+    pub fn get_queen_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
         self.get_rook_moves(square, occupancy) | self.get_bishop_moves(square, occupancy)
     }
 
-    fn get_bishop_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
+    pub fn get_bishop_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
         let index = self.bishop_tbl[square as usize].get_index(occupancy);
         self.bishop_moves[index]
     }
 
-    fn get_rook_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
+    pub fn get_rook_moves(&self, square: u64, occupancy: BitBoard) -> BitBoard {
         let index = self.rook_tbl[square as usize].get_index(occupancy);
         self.rook_moves[index]
     }
@@ -431,17 +432,8 @@ impl MoveGenerator {
             Side::Black => Side::White,
         };
 
-        let opponent_pawns: BitBoard = position.piece_bitboard(Piece::Pawn, opponent);
-        let opponent_knights: BitBoard = position.piece_bitboard(Piece::Knight, opponent);
-        let opponent_rooks: BitBoard = position.piece_bitboard(Piece::Rook, opponent);
-        let opponent_bishop: BitBoard = position.piece_bitboard(Piece::Bishop, opponent);
-        let opponent_queen: BitBoard = position.piece_bitboard(Piece::Queen, opponent);
-
-        (self.get_bishop_moves(king as u64, position.main_bitboard) & opponent_bishop)
-            | (self.get_rook_moves(king as u64, position.main_bitboard) & opponent_rooks)
-            | (self.knight_moves[king as usize] & opponent_knights)
-            | (self.pawn_attacks[side as usize][king as usize] & opponent_pawns)
-            | (self.get_queen_moves(king as u64, position.main_bitboard) & opponent_queen)
+        self.attackers_to(position, king, position.main_bitboard)
+            & position.side_bitboards[opponent as usize]
     }
 
     fn create_moves(
@@ -1,7 +1,9 @@
+pub mod attacks;
 pub mod bitboard;
 pub mod castling_rights;
 pub mod generator;
 pub mod mov;
+pub mod see;
 
 use crate::{
     load_fen, mov::Move, mov::MoveType, mov::NULL_MOVE, BitBoard, MoveGenerator, Piece, Side,
@@ -17,8 +19,10 @@ use std::fmt;
 use strum::IntoEnumIterator;
 
 use self::castling_rights::{Castling, CastlingRights};
+use crate::eval::{material_psqt, piece_square, side_phase, Score, PHASE_WEIGHTS};
 
-pub const MAX_BOARDS: usize = 100;
+// Game history and search path together, about 500 moves
+pub const MAX_BOARDS: usize = 1024;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
 pub struct State {
@@ -28,6 +32,9 @@ pub struct State {
     pub full_move_counter: u8,
     pub current_turn: Side,
     pub zobrist_hash: u64,
+
+    /// Hash of the pawns alone, keys the pawn structure cache
+    pub pawn_hash: u64,
 }
 
 impl State {
@@ -39,6 +46,7 @@ impl State {
             full_move_counter: 1,
             current_turn: Side::White,
             zobrist_hash: 0,
+            pawn_hash: 0,
         }
     }
 
@@ -46,6 +54,10 @@ impl State {
         self.zobrist_hash ^= value;
     }
 
+    fn update_pawn_hash(&mut self, value: u64) {
+        self.pawn_hash ^= value;
+    }
+
     fn change_turn(&mut self) {
         match self.current_turn {
             Side::White => self.current_turn = Side::Black,
@@ -65,19 +77,24 @@ impl State {
     }
 }
 
+/**
+ * Board before every move made, held on the heap so a Position stays small to clone
+ * */
 #[derive(Debug, Clone)]
 pub struct History {
-    pub moves: [Move; MAX_BOARDS],
+    pub moves: Vec<Move>,
 
     /// previous Piece slice board
-    pub prev_pieces: [[Option<(Side, Piece)>; 64]; MAX_BOARDS],
-    pub prev_piece_count: [[[u8; 6]; 2]; MAX_BOARDS],
-    pub prev_main_bitboards: [BitBoard; MAX_BOARDS],
+    pub prev_pieces: Vec<[Option<(Side, Piece)>; 64]>,
+    pub prev_piece_count: Vec<[[u8; 6]; 2]>,
+    pub prev_psqt: Vec<Score>,
+    pub prev_phase: Vec<i32>,
+    pub prev_main_bitboards: Vec<BitBoard>,
 
     /// bitboards respresenting previous empty squares
-    pub prev_side_bitboards: [[BitBoard; 2]; MAX_BOARDS],
-    pub prev_piece_bitboards: [[[BitBoard; 6]; 2]; MAX_BOARDS],
-    pub prev_states: [State; MAX_BOARDS],
+    pub prev_side_bitboards: Vec<[BitBoard; 2]>,
+    pub prev_piece_bitboards: Vec<[[BitBoard; 6]; 2]>,
+    pub prev_states: Vec<State>,
 
     pub prev_white_king: Option<Square>,
     pub prev_black_king: Option<Square>,
@@ -108,13 +125,15 @@ impl Serialize for History {
 impl History {
     fn new() -> History {
         History {
-            moves: [Move(0); MAX_BOARDS],
-            prev_pieces: [[None; 64]; MAX_BOARDS],
-            prev_piece_count: [[[0; 6]; 2]; MAX_BOARDS],
-            prev_main_bitboards: [EMPTY_BITBOARD; MAX_BOARDS],
-            prev_side_bitboards: [[EMPTY_BITBOARD; 2]; MAX_BOARDS],
-            prev_piece_bitboards: [[[EMPTY_BITBOARD; 6]; 2]; MAX_BOARDS],
-            prev_states: [State::new(); MAX_BOARDS],
+            moves: vec![Move(0); MAX_BOARDS],
+            prev_pieces: vec![[None; 64]; MAX_BOARDS],
+            prev_piece_count: vec![[[0; 6]; 2]; MAX_BOARDS],
+            prev_psqt: vec![Score::default(); MAX_BOARDS],
+            prev_phase: vec![0; MAX_BOARDS],
+            prev_main_bitboards: vec![EMPTY_BITBOARD; MAX_BOARDS],
+            prev_side_bitboards: vec![[EMPTY_BITBOARD; 2]; MAX_BOARDS],
+            prev_piece_bitboards: vec![[[EMPTY_BITBOARD; 6]; 2]; MAX_BOARDS],
+            prev_states: vec![State::new(); MAX_BOARDS],
             prev_white_king: None,
             prev_black_king: None,
             count: 0,
@@ -139,6 +158,12 @@ pub struct Position {
 
     pub piece_count: [[u8; 6]; 2],
 
+    /// Material and piece square score, White minus Black, kept up to date by make_move
+    pub psqt: Score,
+
+    /// Game phase of the pieces on the board, above MAX_PHASE after promotions
+    pub phase: i32,
+
     pub state: State,
     pub white_king: Square,
     pub black_king: Square,
@@ -181,6 +206,8 @@ impl Position {
             side_bitboards: [EMPTY_BITBOARD; 2],
             piece_bitboards: [[EMPTY_BITBOARD; 6]; 2],
             piece_count: [[0; 6]; 2],
+            psqt: Score::default(),
+            phase: 0,
 
             state: State::new(),
 
@@ -239,6 +266,9 @@ impl Position {
 
         let mut z = position.zobrist;
         position.state.zobrist_hash = z.generate_hash(&position);
+        position.state.pawn_hash = z.generate_pawn_hash(&position);
+        position.psqt = material_psqt(&position);
+        position.phase = side_phase(&position, Side::White) + side_phase(&position, Side::Black);
 
         Ok(position)
     }
@@ -473,6 +503,64 @@ impl Position {
         }
     }
 
+    /**
+     * Updates the material, piece square and phase accumulators for mv, before it is made
+     * */
+    fn update_accumulators(&mut self, mv: Move) {
+        let side = self.state.current_turn();
+        let opponent = self.state.opponent();
+
+        self.remove_piece_score(mv.piece(), side, mv.from());
+
+        match mv.promotion_piece() {
+            Some(promotion) => self.add_piece_score(promotion, side, mv.target()),
+            None => self.add_piece_score(mv.piece(), side, mv.target()),
+        }
+
+        match mv.move_type() {
+            MoveType::EnPassant => {
+                let captured = match side {
+                    Side::White => Square::from(mv.target() as u64 - 8),
+                    Side::Black => Square::from(mv.target() as u64 + 8),
+                };
+                self.remove_piece_score(Piece::Pawn, opponent, captured);
+            }
+            MoveType::Castle => {
+                let (rook_from, rook_to) = match mv.target() {
+                    WHITE_KINGSIDE_KING => (WHITE_KINGSIDE_ROOK_FROM, WHITE_KINGSIDE_ROOK_TO),
+                    WHITE_QUEENSIDE_KING => (WHITE_QUEENSIDE_ROOK_FROM, WHITE_QUEENSIDE_ROOK_TO),
+                    BLACK_KINGSIDE_KING => (BLACK_KINGSIDE_ROOK_FROM, BLACK_KINGSIDE_ROOK_TO),
+                    _ => (BLACK_QUEENSIDE_ROOK_FROM, BLACK_QUEENSIDE_ROOK_TO),
+                };
+                self.remove_piece_score(Piece::Rook, side, rook_from);
+                self.add_piece_score(Piece::Rook, side, rook_to);
+            }
+            _ => {
+                if let Some((captured_side, captured)) = self.pieces[mv.target() as usize] {
+                    if captured_side == opponent {
+                        self.remove_piece_score(captured, opponent, mv.target());
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_piece_score(&mut self, piece: Piece, side: Side, square: Square) {
+        match side {
+            Side::White => self.psqt += piece_square(piece, side, square as usize),
+            Side::Black => self.psqt -= piece_square(piece, side, square as usize),
+        }
+        self.phase += PHASE_WEIGHTS[piece as usize];
+    }
+
+    fn remove_piece_score(&mut self, piece: Piece, side: Side, square: Square) {
+        match side {
+            Side::White => self.psqt -= piece_square(piece, side, square as usize),
+            Side::Black => self.psqt += piece_square(piece, side, square as usize),
+        }
+        self.phase -= PHASE_WEIGHTS[piece as usize];
+    }
+
     pub fn make_move(&mut self, mv: Move) {
         let from_bitboard: BitBoard = BitBoard(1) << (mv.from() as usize);
         let to_bitboard: BitBoard = BitBoard(1) << (mv.target() as usize);
@@ -500,6 +588,23 @@ impl Position {
                 mv.target(),
             ));
 
+            // Pawn hash, a promoted pawn does not reach the target square as a pawn
+            if mv.piece().is_pawn() {
+                self.state.update_pawn_hash(self.zobrist.rand_piece_num(
+                    self.state.current_turn(),
+                    Piece::Pawn,
+                    mv.from(),
+                ));
+
+                if mv.move_type() != MoveType::Promotion {
+                    self.state.update_pawn_hash(self.zobrist.rand_piece_num(
+                        self.state.current_turn(),
+                        Piece::Pawn,
+                        mv.target(),
+                    ));
+                }
+            }
+
             //Hash castle
             self.state.update_hash(
                 self.zobrist
@@ -511,6 +616,12 @@ impl Position {
             // Update history
             self.history.prev_pieces[self.history.count] = self.pieces;
             self.history.prev_piece_count[self.history.count] = self.piece_count;
+            self.history.prev_psqt[self.history.count] = self.psqt;
+            self.history.prev_phase[self.history.count] = self.phase;
+
+            if self.pieces[mv.from() as usize] == Some((self.state.current_turn(), mv.piece())) {
+                self.update_accumulators(mv);
+            }
             self.history.prev_main_bitboards[self.history.count] = self.main_bitboard;
             self.history.prev_piece_bitboards[self.history.count] = self.piece_bitboards;
             self.history.prev_side_bitboards[self.history.count] = self.side_bitboards;
@@ -600,6 +711,8 @@ impl Position {
         self.piece_bitboards = self.history.prev_piece_bitboards[index];
         self.pieces = self.history.prev_pieces[index];
         self.piece_count = self.history.prev_piece_count[index];
+        self.psqt = self.history.prev_psqt[index];
+        self.phase = self.history.prev_phase[index];
 
         self.history.prev_main_bitboards[index] = EMPTY_BITBOARD;
         self.history.prev_piece_bitboards[index] = [[EMPTY_BITBOARD; 6]; 2];
@@ -625,6 +738,65 @@ impl Position {
         self.history.count -= 1;
     }
 
+    /**
+     * Passes the turn to the opponent without moving a piece
+     *
+     * Only used by the search, must be undone with `unmake_null_move`
+     * */
+    pub fn make_null_move(&mut self) {
+        self.history.prev_states[self.history.count] = self.state;
+        self.history.moves[self.history.count] = NULL_MOVE;
+
+        // Remove en passant square from hash, it can not be captured anymore
+        if let Some(ep) = self.state.en_passant {
+            self.state.update_hash(self.zobrist.rand_en_passant(ep));
+        }
+        self.state.en_passant = None;
+
+        self.state.half_move_counter += 1;
+        self.state.update_hash(self.zobrist.rand_side_num());
+        self.state.change_turn();
+        self.history.count += 1;
+    }
+
+    /**
+     * Restores position back to state it was in before the null move was made
+     * */
+    pub fn unmake_null_move(&mut self) {
+        self.history.count -= 1;
+        self.state = self.history.prev_states[self.history.count];
+    }
+
+    pub fn last_move_was_null(&self) -> bool {
+        self.history.count > 0 && self.history.moves[self.history.count - 1] == NULL_MOVE
+    }
+
+    /**
+     * Returns the move made `plies` moves ago, 1 being the last move
+     *
+     * None if there is no such move or it was a null move
+     * */
+    pub fn previous_move(&self, plies: usize) -> Option<Move> {
+        if plies == 0 || plies > self.history.count {
+            return None;
+        }
+
+        let mv = self.history.moves[self.history.count - plies];
+        (mv != NULL_MOVE).then_some(mv)
+    }
+
+    /**
+     * Returns true if side has any pieces other than pawns and the king
+     * */
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        let pieces = self.piece_bitboard(Piece::Knight, side)
+            | self.piece_bitboard(Piece::Bishop, side)
+            | self.piece_bitboard(Piece::Rook, side)
+            | self.piece_bitboard(Piece::Queen, side);
+
+        pieces != EMPTY_BITBOARD
+    }
+
     fn promote(&mut self, mv: Move, from_bitboard: BitBoard, to_bitboard: BitBoard) {
         let from_to_bitboard: BitBoard = from_bitboard ^ to_bitboard;
 
@@ -772,6 +944,11 @@ impl Position {
             Piece::Pawn,
             Square::from(target_square_num as u64),
         ));
+        self.state.update_pawn_hash(self.zobrist.rand_piece_num(
+            self.state.opponent(),
+            Piece::Pawn,
+            Square::from(target_square_num as u64),
+        ));
     }
 
     pub fn capture(&mut self, mv: Move, from_bitboard: BitBoard, to_bitboard: BitBoard) {
@@ -793,6 +970,14 @@ impl Position {
                 .rand_piece_num(opponent, opponent_piece, mv.target()),
         );
 
+        if opponent_piece.is_pawn() {
+            self.state.update_pawn_hash(self.zobrist.rand_piece_num(
+                opponent,
+                opponent_piece,
+                mv.target(),
+            ));
+        }
+
         // Reset captured piece
         self.piece_bitboards[opponent as usize][opponent_piece as usize] ^= to_bitboard;
 
@@ -849,26 +1034,45 @@ impl Position {
             && move_gen.attacks_to_king(self, side) != EMPTY_BITBOARD
     }
 
-    fn draw_by_fifty_moves(&self) -> bool {
+    pub fn draw_by_fifty_moves(&self) -> bool {
         self.state.half_move_counter >= MAX_HALF_MOVES
     }
 
     fn draw_by_threefold_repetition(&mut self) -> bool {
-        let current_pos_hash = self.state.zobrist_hash;
-        let prev_states = &self.history.prev_states;
+        self.repetitions() >= 2
+    }
+
+    /**
+     * Returns true if the position occurred before, used as a draw by the search since
+     * whatever was played to repeat it can be played again
+     * */
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() >= 1
+    }
 
-        let mut count = 1;
-        for state in prev_states {
-            if state.zobrist_hash == current_pos_hash {
-                count += 1;
+    /**
+     * Number of earlier occurrences of the position, only positions since the last
+     * irreversible move and with the same side to move are compared
+     * */
+    fn repetitions(&self) -> usize {
+        let count = self.history.count;
+        let reversible = (self.state.half_move_counter as usize).min(count);
+        let mut repetitions = 0;
+
+        for plies in 1..=reversible {
+            // Positions before a null move were not really reached from here
+            if self.history.moves[count - plies] == NULL_MOVE {
+                break;
             }
 
-            if count == 3 {
-                return true;
+            if plies % 2 == 0
+                && self.history.prev_states[count - plies].zobrist_hash == self.state.zobrist_hash
+            {
+                repetitions += 1;
             }
         }
 
-        false
+        repetitions
     }
 
     /**
@@ -0,0 +1,129 @@
+use crate::{
+    mov::{Move, MoveType},
+    BitBoard, MoveGenerator, Piece, Position, Side, Square, EIGTH_RANK, EMPTY_BITBOARD, FIRST_RANK,
+};
+
+/// Piece values used to score exchanges, the king can never be won
+pub const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000];
+
+// More captures on one square than there are pieces on the board
+const MAX_EXCHANGES: usize = 32;
+
+impl Position {
+    /**
+     * Static exchange evaluation, the material the side to move wins with mv when both sides
+     * keep recapturing on its target square with their least valuable piece, and either side
+     * may stop when recapturing would lose more
+     *
+     * Sliding pieces behind the capturing pieces join the exchange once they are uncovered.
+     * Pins are not taken into account.
+     * */
+    pub fn see(&self, move_gen: &MoveGenerator, mv: Move) -> i32 {
+        if mv.move_type() == MoveType::Castle {
+            return 0;
+        }
+
+        let from = mv.from();
+        let target = mv.target();
+        let mut occupancy = self.main_bitboard;
+        occupancy.clear_bit(from);
+
+        let captured = match mv.move_type() {
+            MoveType::EnPassant => {
+                // The captured pawn is beside the target square, not on it
+                let captured_square = match self.state.current_turn() {
+                    Side::White => Square::from(target as u64 - 8),
+                    Side::Black => Square::from(target as u64 + 8),
+                };
+                occupancy.clear_bit(captured_square);
+                SEE_VALUES[Piece::Pawn as usize]
+            }
+            _ => self.pieces[target as usize].map_or(0, |(_, piece)| SEE_VALUES[piece as usize]),
+        };
+
+        // gains[d] is what the side making capture d wins if the exchange stops after it
+        let mut gains = [0; MAX_EXCHANGES];
+        gains[0] = captured;
+
+        // Value of the piece standing on the target square, the next one to be captured
+        let mut on_target = match mv.promotion_piece() {
+            Some(promotion) => {
+                gains[0] += SEE_VALUES[promotion as usize] - SEE_VALUES[Piece::Pawn as usize];
+                SEE_VALUES[promotion as usize]
+            }
+            None => SEE_VALUES[mv.piece() as usize],
+        };
+
+        let promotion_ranks = FIRST_RANK | EIGTH_RANK;
+        let promotes = promotion_ranks.get_bit(target as u64) == 1;
+        let mut attackers = move_gen.attackers_to(self, target, occupancy);
+        let mut side = self.state.opponent();
+        let mut depth = 0;
+
+        while depth + 1 < MAX_EXCHANGES {
+            let side_attackers = attackers & self.side_bitboards[side as usize];
+            let (piece, from) = match self.least_valuable_attacker(side_attackers, side) {
+                Some(attacker) => attacker,
+                None => break,
+            };
+
+            // The king can not capture onto a square the other side still attacks
+            let opponent_attackers = attackers & !side_attackers;
+            if piece == Piece::King && opponent_attackers != EMPTY_BITBOARD {
+                break;
+            }
+
+            depth += 1;
+            gains[depth] = on_target - gains[depth - 1];
+            on_target = SEE_VALUES[piece as usize];
+
+            if piece == Piece::Pawn && promotes {
+                gains[depth] +=
+                    SEE_VALUES[Piece::Queen as usize] - SEE_VALUES[Piece::Pawn as usize];
+                on_target = SEE_VALUES[Piece::Queen as usize];
+            }
+
+            occupancy.clear_bit(from);
+            attackers = move_gen.attackers_to(self, target, occupancy);
+            side = match side {
+                Side::White => Side::Black,
+                Side::Black => Side::White,
+            };
+        }
+
+        // Each side picks between stopping and capturing, from the last capture back
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+
+        gains[0]
+    }
+
+    /**
+     * True if mv wins at least threshold in the exchange on its target square
+     * */
+    pub fn see_ge(&self, move_gen: &MoveGenerator, mv: Move, threshold: i32) -> bool {
+        self.see(move_gen, mv) >= threshold
+    }
+
+    fn least_valuable_attacker(&self, attackers: BitBoard, side: Side) -> Option<(Piece, Square)> {
+        [
+            Piece::Pawn,
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+            Piece::King,
+        ]
+        .into_iter()
+        .find_map(|piece| {
+            let pieces = attackers & self.piece_bitboard(piece, side);
+            if pieces == EMPTY_BITBOARD {
+                None
+            } else {
+                Some((piece, pieces.bitscan_forward()))
+            }
+        })
+    }
+}
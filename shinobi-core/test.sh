@@ -1,2 +1,2 @@
 #!/bin/sh
-RUST_MIN_STACK=104857600 cargo test $1 -- --nocapture
+cargo test $1 -- --nocapture
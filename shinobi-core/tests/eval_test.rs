@@ -0,0 +1,110 @@
+use shinobi_core::{
+    bench::BENCH_POSITIONS,
+    eval::{evaluate, game_phase, trace, MAX_PHASE},
+    pawns::{evaluate_pawns, PawnTable},
+    pieces::{bishop_pair, king_safety, mobility, outposts, rooks},
+    BitBoard, MoveGenerator, Position, Side, EMPTY_BITBOARD, START_POS,
+};
+
+fn eval_fen(fen: &str) -> i32 {
+    let position = Position::from_fen(fen).unwrap();
+    evaluate(&position, &MoveGenerator::new(), &mut PawnTable::new())
+}
+
+#[test]
+fn tapered_eval_is_symmetric_and_counts_material() {
+    let start = Position::from_fen(START_POS).unwrap();
+    assert_eq!(game_phase(&start), MAX_PHASE);
+    assert_eq!(eval_fen(START_POS), 0);
+
+    // Same position with Black to move scores the same for the side to move
+    assert_eq!(
+        eval_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"),
+        0
+    );
+
+    // White is a queen up
+    let queen_up = "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    assert!(game_phase(&Position::from_fen(queen_up).unwrap()) < MAX_PHASE);
+    assert!(eval_fen(queen_up) > 800);
+}
+
+#[test]
+fn king_centralizes_in_endgame() {
+    let centre = "8/8/8/8/3K4/8/4p3/4k3 w - - 0 1";
+    assert_eq!(game_phase(&Position::from_fen(centre).unwrap()), 0);
+    assert!(eval_fen(centre) > eval_fen("8/8/8/8/8/8/4p3/K3k3 w - - 0 1"));
+
+    // With the queens and rooks still on, the king belongs on its back rank
+    assert!(
+        eval_fen("r2qk2r/pppppppp/8/8/8/8/PPPPPPPP/R2Q1RK1 w kq - 0 1")
+            > eval_fen("r2qk2r/pppppppp/8/8/3K4/8/PPPPPPPP/R2Q1R2 w kq - 0 1")
+    );
+}
+
+#[test]
+fn pawn_structure() {
+    // Connected pawns against isolated and doubled ones, nothing is passed
+    let position = Position::from_fen("4k3/2p1p3/2p5/8/8/8/2PPP3/4K3 w - - 0 1").unwrap();
+    let entry = evaluate_pawns(&position);
+    assert!(entry.score.mg > 0 && entry.score.eg > 0);
+    assert_eq!(entry.passed, [EMPTY_BITBOARD; 2]);
+
+    // Cached entries match a fresh evaluation
+    let mut table = PawnTable::new();
+    assert_eq!(table.probe(&position), entry);
+    assert_eq!(table.probe(&position), entry);
+
+    let passer = Position::from_fen("8/8/4k3/P7/8/8/8/7K w - - 0 1").unwrap();
+    assert_eq!(evaluate_pawns(&passer).passed[0], BitBoard(1 << 32));
+
+    // The black king can catch the pawn only when it is its move
+    let white_to_move = eval_fen("8/8/4k3/P7/8/8/8/7K w - - 0 1");
+    let black_to_move = -eval_fen("8/8/4k3/P7/8/8/8/7K b - - 0 1");
+    assert!(white_to_move > 500);
+    assert!(black_to_move < 300);
+}
+
+#[test]
+fn piece_activity() {
+    let move_gen = MoveGenerator::new();
+
+    // Rook on the open e file and the seventh rank, knight on a supported d5 outpost,
+    // only White has the bishop pair
+    let position = Position::from_fen("6k1/p3R3/5ppp/3N4/2P5/8/5PPP/2B1B1K1 w - - 0 1").unwrap();
+    assert!(rooks(&position, Side::White, &mut ()).mg >= 45);
+    assert!(outposts(&position, &move_gen, Side::White, &mut ()).mg > 0);
+    assert!(bishop_pair(&position, Side::White, &mut ()).eg > 0);
+    assert_eq!(bishop_pair(&position, Side::Black, &mut ()).eg, 0);
+
+    // Pieces boxed in by their own pawns move less than in the open
+    let start = Position::from_fen(START_POS).unwrap();
+    let open = Position::from_fen("4k3/8/8/8/8/8/8/RNBQKBNR w - - 0 1").unwrap();
+    let boxed_in = mobility(&start, &move_gen, Side::White, &mut ()).mg;
+    assert!(mobility(&open, &move_gen, Side::White, &mut ()).mg > boxed_in);
+
+    // A queen and rook bearing down on the king cost more than the shield gives back
+    let shelter = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+    let attacked = Position::from_fen("6k1/8/8/8/8/8/5q1r/6K1 w - - 0 1").unwrap();
+    assert!(king_safety(&shelter, &move_gen, Side::White, &mut ()).mg > 0);
+    assert!(king_safety(&attacked, &move_gen, Side::White, &mut ()).mg < -100);
+}
+
+#[test]
+fn trace_matches_evaluate() {
+    let move_gen = MoveGenerator::new();
+
+    for fen in BENCH_POSITIONS {
+        let position = Position::from_fen(fen).unwrap();
+        let trace = trace(&position, &move_gen);
+
+        assert_eq!(
+            trace.score,
+            evaluate(&position, &move_gen, &mut PawnTable::new()),
+            "{}",
+            fen
+        );
+        let last_line = format!("Final evaluation: {} (side to move)", trace.score);
+        assert!(trace.to_string().ends_with(&last_line));
+    }
+}
@@ -0,0 +1,193 @@
+use shinobi_core::{
+    eval::{material_psqt, side_phase},
+    mov::{Move, MoveType},
+    BitBoard, MoveGenerator, Piece, Position, Side, Square, Zobrist, EMPTY_BITBOARD, START_POS,
+};
+
+const EN_PASSANT_POS: &str = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+
+#[test]
+fn null_move_hash() {
+    let mut position = Position::from_fen(EN_PASSANT_POS).unwrap();
+    let original = position.state;
+
+    position.make_null_move();
+    assert_eq!(position.state.current_turn(), Side::Black);
+    assert_eq!(position.state.en_passant, None);
+    assert_eq!(
+        position.state.zobrist_hash,
+        Zobrist::new().generate_hash(&position)
+    );
+    assert!(position.last_move_was_null());
+
+    position.unmake_null_move();
+    assert_eq!(position.state, original);
+    assert!(!position.last_move_was_null());
+}
+
+fn assert_incremental_state(position: &Position, zobrist: &Zobrist) {
+    assert_eq!(
+        position.state.pawn_hash,
+        zobrist.generate_pawn_hash(position)
+    );
+    assert_eq!(position.psqt, material_psqt(position));
+    assert_eq!(
+        position.phase,
+        side_phase(position, Side::White) + side_phase(position, Side::Black)
+    );
+}
+
+#[test]
+fn incremental_state_follows_moves() {
+    // En passant, castling, pawn captures and promotions with and without captures
+    let mut position =
+        Position::from_fen("r3k2r/1P2p3/8/2pP4/8/8/6p1/R3K2R w KQkq c6 0 1").unwrap();
+    let move_gen = MoveGenerator::new();
+    let zobrist = Zobrist::new();
+    let original = (position.psqt, position.phase);
+
+    let moves = move_gen.generate_legal_moves(&mut position, Side::White, MoveType::All);
+    for i in 0..moves.len() {
+        position.make_move(moves.get(i));
+        assert_incremental_state(&position, &zobrist);
+
+        let replies = move_gen.generate_legal_moves(&mut position, Side::Black, MoveType::All);
+        for j in 0..replies.len() {
+            position.make_move(replies.get(j));
+            assert_incremental_state(&position, &zobrist);
+            position.unmake();
+        }
+
+        position.unmake();
+    }
+
+    assert_eq!((position.psqt, position.phase), original);
+}
+
+#[test]
+fn non_pawn_material() {
+    let position = Position::from_fen("4k3/pppp4/8/8/8/8/4PPPP/3NK3 w - - 0 1").unwrap();
+
+    assert!(position.has_non_pawn_material(Side::White));
+    assert!(!position.has_non_pawn_material(Side::Black));
+}
+
+#[test]
+fn previous_move_skips_null_moves() {
+    let mut position = Position::from_fen(EN_PASSANT_POS).unwrap();
+    let exf6 = Move::init(Piece::Pawn, Square::E5, Square::F6, MoveType::EnPassant);
+
+    assert_eq!(position.previous_move(1), None);
+
+    position.make_move(exf6);
+    position.make_null_move();
+    assert_eq!(position.previous_move(1), None);
+    assert_eq!(position.previous_move(2), Some(exf6));
+    assert_eq!(position.previous_move(3), None);
+}
+
+#[test]
+fn repetition_since_irreversible_move() {
+    let mut position = Position::from_fen(START_POS).unwrap();
+    let nf3 = Move::init(Piece::Knight, Square::G1, Square::F3, MoveType::Quiet);
+    let nf6 = Move::init(Piece::Knight, Square::G8, Square::F6, MoveType::Quiet);
+    let ng1 = Move::init(Piece::Knight, Square::F3, Square::G1, MoveType::Quiet);
+    let ng8 = Move::init(Piece::Knight, Square::F6, Square::G8, MoveType::Quiet);
+    let e3 = Move::init(Piece::Pawn, Square::E2, Square::E3, MoveType::Quiet);
+
+    for mv in [nf3, nf6, ng1] {
+        position.make_move(mv);
+        assert!(!position.is_repetition());
+    }
+
+    position.make_move(ng8);
+    assert!(position.is_repetition());
+
+    // Only positions after the pawn move are compared from here on
+    position.make_move(e3);
+    for mv in [nf6, nf3, ng8] {
+        position.make_move(mv);
+        assert!(!position.is_repetition());
+    }
+
+    position.make_move(ng1);
+    assert!(position.is_repetition());
+}
+
+#[test]
+fn see_scores_exchanges() {
+    let move_gen = MoveGenerator::new();
+
+    // Undefended pawn
+    let position = Position::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+    let rxe5 = Move::init(Piece::Rook, Square::E1, Square::E5, MoveType::Capture);
+    assert_eq!(position.see(&move_gen, rxe5), 100);
+
+    // Pawn defended by a rook, the second white rook only joins through the x-ray
+    let position = Position::from_fen("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1").unwrap();
+    let rxe5 = Move::init(Piece::Rook, Square::E2, Square::E5, MoveType::Capture);
+    assert_eq!(position.see(&move_gen, rxe5), 100);
+    assert!(position.see_ge(&move_gen, rxe5, 0));
+
+    // Knight for a pawn
+    let position =
+        Position::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+    let nxe5 = Move::init(Piece::Knight, Square::D3, Square::E5, MoveType::Capture);
+    assert_eq!(position.see(&move_gen, nxe5), -220);
+    assert!(!position.see_ge(&move_gen, nxe5, 0));
+}
+
+#[test]
+fn see_handles_en_passant_and_promotions() {
+    let move_gen = MoveGenerator::new();
+
+    // The pawn taken en passant is defended by nothing
+    let position = Position::from_fen(EN_PASSANT_POS).unwrap();
+    let exf6 = Move::init(Piece::Pawn, Square::E5, Square::F6, MoveType::EnPassant);
+    assert_eq!(position.see(&move_gen, exf6), 0);
+
+    // The new queen is taken by the rook, losing the pawn
+    let position = Position::from_fen("7r/P7/8/8/8/8/6k1/K7 w - - 0 1").unwrap();
+    let a8q = Move::init_with_promotion_piece(
+        Piece::Pawn,
+        Square::A7,
+        Square::A8,
+        MoveType::Promotion,
+        Piece::Queen,
+    );
+    assert_eq!(position.see(&move_gen, a8q), -100);
+}
+
+#[test]
+fn attack_queries() {
+    let move_gen = MoveGenerator::new();
+
+    // Knight pinned by the rook
+    let position = Position::from_fen("4k3/8/8/8/4r3/8/4N3/4K2b w - - 0 1").unwrap();
+    assert_eq!(
+        move_gen.pinned_pieces(&position, Side::White),
+        BitBoard(1 << Square::E2 as u64)
+    );
+    assert_eq!(move_gen.checkers(&position), EMPTY_BITBOARD);
+    assert!(move_gen.is_square_attacked(&position, Square::E3, Side::Black));
+    assert!(!move_gen.is_square_attacked(&position, Square::A8, Side::Black));
+
+    // The kings attack each other's squares too
+    let position = Position::from_fen("8/8/8/3k4/8/3K4/8/8 w - - 0 1").unwrap();
+    assert!(move_gen.is_square_attacked(&position, Square::D4, Side::Black));
+    assert!(move_gen.is_square_attacked(&position, Square::D4, Side::White));
+
+    let position = Position::from_fen("4k3/8/8/3n4/8/8/8/3RK3 b - - 0 1").unwrap();
+    assert_eq!(
+        move_gen.hanging_pieces(&position, Side::Black),
+        BitBoard(1 << Square::D5 as u64)
+    );
+    assert_eq!(
+        move_gen.hanging_pieces(&position, Side::White),
+        EMPTY_BITBOARD
+    );
+    assert_eq!(
+        move_gen.attackers_to(&position, Square::D5, position.main_bitboard),
+        BitBoard(1 << Square::D1 as u64)
+    );
+}
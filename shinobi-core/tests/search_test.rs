@@ -0,0 +1,352 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use shinobi_core::{
+    bench::{bench, BENCH_POSITIONS},
+    evaluator::Evaluator,
+    handle::SearchHandle,
+    limits::SearchLimits,
+    mov::{Move, MoveType},
+    observer::{SearchObserver, SearchProgress},
+    search::{mate_in, uci_score, Search},
+    tt::{Entry, HASH_FLAG_BETA, TT},
+    MoveGenerator, Piece, Position, Square, START_POS,
+};
+
+const FEW_MOVES_POS: &str = "k7/8/1K6/8/8/8/8/7R w - - 0 1";
+const MATE_IN_TWO_POS: &str = "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1";
+
+#[test]
+fn multi_pv_lines_sorted_by_score() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+    search.multi_pv = 4;
+
+    let lines = search
+        .go(&position, &move_gen, SearchLimits::depth(3))
+        .lines;
+
+    assert_eq!(lines.len(), 4);
+    for pair in lines.windows(2) {
+        assert!(pair[0].score >= pair[1].score);
+        assert_ne!(pair[0].best_move(), pair[1].best_move());
+    }
+    assert_eq!(search.best_move, lines[0].best_move());
+}
+
+#[test]
+fn multi_pv_limited_by_legal_moves() {
+    let position = Position::from_fen(FEW_MOVES_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+    search.multi_pv = 64;
+
+    let lines = search
+        .go(&position, &move_gen, SearchLimits::depth(2))
+        .lines;
+
+    // 14 rook moves and 6 king moves
+    assert_eq!(lines.len(), 20);
+}
+
+#[test]
+fn search_moves_restrict_root() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+    search.multi_pv = 3;
+
+    let a2a3 = Move::init(Piece::Pawn, Square::A2, Square::A3, MoveType::Quiet);
+    let h2h3 = Move::init(Piece::Pawn, Square::H2, Square::H3, MoveType::Quiet);
+    let limits = SearchLimits {
+        depth: Some(2),
+        search_moves: vec![a2a3, h2h3],
+        ..SearchLimits::default()
+    };
+    let lines = search.go(&position, &move_gen, limits).lines;
+
+    assert_eq!(lines.len(), 2);
+    for line in lines.iter() {
+        assert!(line.best_move() == Some(a2a3) || line.best_move() == Some(h2h3));
+    }
+}
+
+#[test]
+fn tt_round_trip() {
+    let tt = TT::new();
+    let entry = Entry {
+        hash: 0xDEAD_BEEF_1234_5678,
+        depth: 9,
+        flags: HASH_FLAG_BETA,
+        score: -28950,
+        best: Move::init(Piece::Knight, Square::G1, Square::F3, MoveType::Quiet),
+    };
+
+    tt.put(entry.hash, entry);
+
+    assert_eq!(tt.get(entry.hash), Some(entry));
+    assert_eq!(tt.get(entry.hash ^ 1), None);
+}
+
+#[test]
+fn tt_slot_shows_other_positions() {
+    let tt = TT::with_size(1);
+    let entry = Entry {
+        hash: 0xDEAD_BEEF_1234_5678,
+        depth: 9,
+        flags: HASH_FLAG_BETA,
+        score: 120,
+        best: Move::init(Piece::Knight, Square::G1, Square::F3, MoveType::Quiet),
+    };
+    assert_eq!(tt.slot(entry.hash), None);
+
+    // Every hash shares the one slot
+    tt.put(entry.hash, entry);
+    assert_eq!(tt.get(entry.hash ^ 1), None);
+    assert_eq!(tt.slot(entry.hash ^ 1), Some(entry));
+}
+
+#[test]
+fn mate_found_ends_search() {
+    let position = Position::from_fen(MATE_IN_TWO_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+
+    let lines = search
+        .go(&position, &move_gen, SearchLimits::depth(7))
+        .lines;
+
+    let a1a6 = Move::init(Piece::Rook, Square::A1, Square::A6, MoveType::Quiet);
+    assert_eq!(mate_in(lines[0].score), Some(2));
+    assert_eq!(uci_score(lines[0].score), "mate 2");
+    assert_eq!(lines[0].best_move(), Some(a1a6));
+
+    // Mate in 2 is 3 plies deep, late move reductions may hide it for an iteration or so but
+    // the search stops well before depth 7
+    assert!(lines[0].depth < 7);
+}
+
+#[derive(Default)]
+struct IterationCounter {
+    depths: Vec<i32>,
+}
+
+impl SearchObserver for IterationCounter {
+    fn on_iteration(&mut self, progress: &SearchProgress) {
+        self.depths.push(progress.depth);
+    }
+}
+
+#[test]
+fn go_reports_iterations_and_result() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let observer = Arc::new(Mutex::new(IterationCounter::default()));
+    let mut search = Search::new();
+    search.set_observer(observer.clone());
+
+    let result = search.go(&position, &move_gen, SearchLimits::depth(4));
+
+    assert_eq!(observer.lock().unwrap().depths, vec![1, 2, 3, 4]);
+    assert_eq!(result.depth, 4);
+    assert_eq!(result.best_move, result.pv.first().copied());
+    assert_eq!(result.ponder, result.pv.get(1).copied());
+    assert_eq!(result.lines.len(), 1);
+    assert!(result.nodes > 0);
+    assert!(!search.searching.load(Ordering::Relaxed));
+}
+
+#[test]
+fn depth_zero_searches_one_ply() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+
+    for depth in [0, -1] {
+        let result = search.go(&position, &move_gen, SearchLimits::depth(depth));
+
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move.is_some());
+    }
+}
+
+#[test]
+fn timed_search_is_not_depth_limited() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+
+    // The default depth is reached long before the time runs out
+    let result = search.go(&position, &move_gen, SearchLimits::move_time(1000));
+
+    assert!(result.time >= 500);
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn stopped_infinite_search_reports_a_move() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let limits = SearchLimits {
+        infinite: true,
+        ..SearchLimits::default()
+    };
+
+    let mut handle = SearchHandle::spawn(
+        Search::new(),
+        position,
+        Arc::new(MoveGenerator::new()),
+        limits,
+    )
+    .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(handle.is_searching());
+
+    let result = handle.stop_and_wait().unwrap();
+    assert!(result.best_move.is_some());
+    assert!(!handle.is_searching());
+}
+
+#[test]
+fn handle_searches_to_depth_on_its_own_stack() {
+    // Nothing raises the stack of the test threads, the search thread sizes its own
+    let position = Position::from_fen(START_POS).unwrap();
+    let mut handle = SearchHandle::spawn(
+        Search::new(),
+        position,
+        Arc::new(MoveGenerator::new()),
+        SearchLimits::depth(6),
+    )
+    .unwrap();
+
+    let result = handle.wait().unwrap();
+    assert_eq!(result.depth, 6);
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn node_limited_search_is_reproducible() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+
+    let mut fresh = Search::new();
+    let expected = fresh.go(&position, &move_gen, SearchLimits::nodes(20_000));
+    assert_eq!(expected.nodes, 20_000);
+
+    // Tables filled by an earlier search are cleared first
+    let mut search = Search::new();
+    search.params.set_option("Deterministic", "true").unwrap();
+    search.go(&position, &move_gen, SearchLimits::depth(5));
+    let result = search.go(&position, &move_gen, SearchLimits::nodes(20_000));
+
+    assert_eq!(result.best_move, expected.best_move);
+    assert_eq!(result.nodes, expected.nodes);
+    assert_eq!(result.pv, expected.pv);
+}
+
+#[test]
+fn bench_signature_is_stable() {
+    let move_gen = MoveGenerator::new();
+
+    for fen in BENCH_POSITIONS {
+        assert!(Position::from_fen(fen).is_ok(), "{}", fen);
+    }
+
+    let first = bench(&move_gen, 3);
+    assert!(first.nodes > 0);
+    assert_eq!(bench(&move_gen, 3).nodes, first.nodes);
+}
+
+#[test]
+fn skill_level_plays_one_of_the_lines() {
+    let position = Position::from_fen(START_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+    search.params.set_option("Skill Level", "0").unwrap();
+    search.params.set_option("Deterministic", "true").unwrap();
+
+    let result = search.go(&position, &move_gen, SearchLimits::depth(6));
+
+    assert_eq!(result.depth, 1);
+    assert_eq!(result.lines.len(), 4);
+    assert!(result
+        .lines
+        .iter()
+        .any(|line| line.best_move() == result.best_move && line.moves == result.pv));
+    assert_eq!(search.multi_pv, 1);
+
+    let again = search.go(&position, &move_gen, SearchLimits::depth(6));
+    assert_eq!(again.best_move, result.best_move);
+}
+
+#[test]
+fn quiescence_does_not_stand_pat_in_check() {
+    let position = Position::from_fen("4r1k1/4rppp/8/8/8/8/P4PPP/4R1K1 w - - 0 1").unwrap();
+    let move_gen = MoveGenerator::new();
+    let mut search = Search::new();
+
+    // After a3 the back rank mate Rxe1 is a capture, only found by quiescence
+    let a2a3 = Move::init(Piece::Pawn, Square::A2, Square::A3, MoveType::Quiet);
+    let limits = SearchLimits {
+        depth: Some(1),
+        search_moves: vec![a2a3],
+        ..SearchLimits::default()
+    };
+    let result = search.go(&position, &move_gen, limits);
+
+    assert_eq!(mate_in(result.score), Some(-1));
+}
+
+/// Scores every position as a draw and checks the hooks follow the search tree
+#[derive(Clone, Default)]
+struct DrawEvaluator {
+    keys: Vec<u64>,
+    evaluations: Arc<AtomicU64>,
+}
+
+impl Evaluator for DrawEvaluator {
+    fn evaluate(&mut self, _position: &Position, _move_gen: &MoveGenerator) -> i32 {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        0
+    }
+
+    fn on_new_search(&mut self, _position: &Position) {
+        assert!(self.keys.is_empty());
+    }
+
+    fn on_make_move(&mut self, position: &Position, _mv: Move) {
+        self.keys.push(position.state.zobrist_hash);
+    }
+
+    fn on_unmake_move(&mut self, position: &Position) {
+        assert_eq!(self.keys.pop(), Some(position.state.zobrist_hash));
+    }
+
+    fn on_make_null_move(&mut self, position: &Position) {
+        self.keys.push(position.state.zobrist_hash);
+    }
+
+    fn on_unmake_null_move(&mut self, position: &Position) {
+        assert_eq!(self.keys.pop(), Some(position.state.zobrist_hash));
+    }
+
+    fn box_clone(&self) -> Box<dyn Evaluator> {
+        Box::new(self.clone())
+    }
+}
+
+#[test]
+fn custom_evaluator_is_used() {
+    let position = Position::from_fen(MATE_IN_TWO_POS).unwrap();
+    let move_gen = MoveGenerator::new();
+    let evaluator = DrawEvaluator::default();
+    let evaluations = evaluator.evaluations.clone();
+    let mut search = Search::new();
+    search.set_evaluator(evaluator);
+
+    // Mates are found by the search whatever the evaluation says
+    let result = search.go(&position, &move_gen, SearchLimits::depth(5));
+
+    assert_eq!(mate_in(result.score), Some(2));
+    assert!(evaluations.load(Ordering::Relaxed) > 0);
+}
@@ -0,0 +1,72 @@
+use shinobi_core::{
+    bench::BENCH_POSITIONS,
+    eval::{evaluate, Param},
+    pawns::PawnTable,
+    tune::{parse_entry, quiet_position, quiet_search, Tuner},
+    MoveGenerator, Piece, Position, Side, Square,
+};
+
+#[test]
+fn linear_eval_matches_evaluate() {
+    let move_gen = MoveGenerator::new();
+    let tuner = Tuner::new();
+
+    for fen in BENCH_POSITIONS {
+        let position = Position::from_fen(fen).unwrap();
+        let score = evaluate(&position, &move_gen, &mut PawnTable::new());
+        let white_score = match position.state.current_turn() {
+            Side::White => score,
+            Side::Black => -score,
+        };
+
+        // Only the rounding of the integer evaluation differs
+        let linear = tuner.linear_eval(&position, &move_gen);
+        assert!((linear - white_score as f64).abs() <= 2.0, "{}", fen);
+    }
+}
+
+#[test]
+fn dataset_lines_become_quiet_positions() {
+    let (_, result) =
+        parse_entry("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [0.5]").unwrap();
+    assert_eq!(result, 0.5);
+
+    let (position, result) = parse_entry("4k3/8/8/3r4/8/8/8/3QK3 w - - c9 \"0-1\";").unwrap();
+    assert_eq!(result, 0.0);
+    assert!(parse_entry("4k3/8/8/3r4/8/8/8/3QK3 w - - 0 1").is_err());
+
+    // The hanging rook is taken before the position is scored
+    let quiet = quiet_position(position, &MoveGenerator::new(), &mut quiet_search());
+    assert_eq!(
+        quiet.pieces[Square::D5 as usize],
+        Some((Side::White, Piece::Queen))
+    );
+}
+
+#[test]
+fn tuning_lowers_error() {
+    let move_gen = MoveGenerator::new();
+    let mut tuner = Tuner::new();
+
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let position = Position::from_fen(fen).unwrap();
+        tuner.add_position(position, (i % 3) as f64 / 2.0, &move_gen);
+    }
+    assert_eq!(tuner.positions(), BENCH_POSITIONS.len());
+
+    // Untuned output is the evaluation as it stands
+    let source = tuner.to_rust();
+    assert!(source.contains("pub const PIECE_VALUES: [Score; 6] = [\n    Score::new(82, 94),"));
+    assert!(source.contains("pub const PASSED_THEIR_KING_DISTANCE: i32 = 5;"));
+    assert!(source.contains("const EG_KING_SQ: [i32; 64] = ["));
+
+    tuner.fit_k();
+    let error = tuner.error();
+    let pawn = tuner.value(Param::PieceValue(Piece::Pawn));
+    for _ in 0..20 {
+        tuner.epoch(1.0);
+    }
+
+    assert!(tuner.error() < error);
+    assert_ne!(tuner.value(Param::PieceValue(Piece::Pawn)), pawn);
+}
@@ -37,6 +37,22 @@ impl ClientEngine {
         }
     }
 
+    /**
+     * Sets a search option like "Skill Level", "UCI_LimitStrength" or "UCI_Elo"
+     * */
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.search.params.set_option(name, value)
+    }
+
+    /**
+     * Searches the loaded position for move_time milliseconds and returns a move like "e2e4"
+     * */
+    pub fn best_move(&mut self, move_time: i32) -> Option<String> {
+        let limits = limits::SearchLimits::move_time(move_time);
+        let result = self.search.go(&self.position, &self.move_gen, limits);
+        result.best_move.map(|mv| mv.to_string())
+    }
+
     pub fn start_perft(&mut self, depth: u32) -> u64 {
         let _ = console_log::init_with_level(Level::Debug);
         info!("STARTING PERFT");
@@ -82,4 +98,33 @@ mod tests {
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn best_move_returns_within_move_time() {
+        let mut engine = ClientEngine::new();
+        let start = std::time::Instant::now();
+
+        // Leaves room for a busy machine, the search itself stops at the budget
+        assert!(engine.best_move(200).is_some());
+        assert!(start.elapsed().as_millis() < 200 + 1000);
+    }
+
+    #[test]
+    fn larger_budget_searches_deeper() {
+        let mut small = ClientEngine::new();
+        let mut large = ClientEngine::new();
+
+        // Node limits stand in for move times so the depths are the same on every run
+        let shallow = small.search.go(
+            &small.position,
+            &small.move_gen,
+            limits::SearchLimits::nodes(2_000),
+        );
+        let deep = large.search.go(
+            &large.position,
+            &large.move_gen,
+            limits::SearchLimits::nodes(100_000),
+        );
+        assert!(deep.depth > shallow.depth);
+    }
 }